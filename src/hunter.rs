@@ -1,33 +1,76 @@
 pub mod util;
 
+mod api;
+pub use api::*;
+
+mod approval;
+pub use approval::*;
+
+mod audit;
+pub use audit::*;
+
+mod bot;
+pub use bot::*;
+
 mod configuration;
 pub use configuration::*;
 
+mod control;
+pub use control::*;
+
+mod diagnosis;
+
+mod endpoint;
+pub use endpoint::*;
+
+mod error;
+pub use error::*;
+
 mod graphql;
 
+mod index;
+pub use index::*;
+
+mod journal;
+pub use journal::*;
+
+mod light_client;
+pub use light_client::*;
+
+mod metrics;
+pub use metrics::*;
+
+mod names;
+pub use names::*;
+
 mod node;
 
 mod notification;
 pub use notification::*;
 
+mod supervisor;
+
 mod tx;
 
 pub use crate::prelude::*;
 
 // std
-use std::{mem, sync::Arc, thread, time::Duration};
+use std::{
+	collections::{HashMap, HashSet},
+	mem,
+	sync::{Arc, Mutex},
+	time::Duration,
+};
 // crates.io
 use jsonrpsee::{
 	async_client::{Client as WsClient, ClientBuilder as WsClientBuilder},
 	client_transport::ws::WsTransportClientBuilder,
 };
 use reqwest::Client;
-use scale_value::ValueDef;
 #[cfg(feature = "node-test")] use sp_core::{sr25519::Pair, Pair as _};
 #[cfg(feature = "node-test")] use subxt::tx::PairSigner;
 use subxt::{backend::rpc::RpcClient, config::polkadot::H256, OnlineClient, PolkadotConfig};
 
-type BlockStream = subxt::backend::StreamOf<std::result::Result<Block, subxt::Error>>;
 type Block =
 	subxt::blocks::Block<subxt::PolkadotConfig, subxt::OnlineClient<subxt::PolkadotConfig>>;
 
@@ -37,21 +80,43 @@ const E_STATE_AUCTION_MUST_BE_SOME: &str = "`state.auction` must be some";
 pub struct Hunter {
 	pub configuration: Configuration,
 	pub http: Client,
-	_ws_connection: Arc<WsClient>,
+	/// RPC client of the active endpoint.
+	rpc: RpcClient,
+	/// Client of the active endpoint.
 	pub node: OnlineClient<PolkadotConfig>,
+	endpoints: Vec<Endpoint>,
+	/// Index of the active endpoint.
+	active: usize,
+	/// Token of the network, detected from the chain.
+	pub token: Token,
+	pub names: Names,
 	pub auction_ending_period: BlockNumber,
 	pub auction_sample_length: BlockNumber,
 	pub bidder: AccountId,
+	journal: Option<RecordWriter>,
+	/// Behind a lock, so the decisions are written as soon as they are made.
+	audit: Option<Mutex<AuditLog>>,
+	pub status: SharedStatus,
+	pub metrics: Arc<Metrics>,
+	deliveries: DeliveryQueue,
+	throttles: Throttles,
+	/// Open incidents and the auctions in which they were raised.
+	incidents: Mutex<HashMap<Condition, Option<u32>>>,
+	commands: Commands,
+	paused: bool,
+	/// Configuration fields overridden through the control API since the last loading.
+	overrides: HashSet<&'static str>,
 }
 impl Hunter {
 	#[allow(unused)]
 	#[cfg(feature = "node-test")]
 	async fn tester() -> Self {
 		let configuration = Configuration {
-			graphql_endpoint: "http://127.0.0.1:3000/graphql".into(),
-			node_endpoint: "ws://127.0.0.1:9944".into(),
+			graphql_endpoint: Some("http://127.0.0.1:3000/graphql".into()),
+			graphql_schema: GraphqlSchema::Firesquid,
+			node_endpoints: vec!["ws://127.0.0.1:9944".into()],
 			block_subscription_mode: BlockSubscriptionMode::Best,
-			token: Token { symbol: "UNIT", decimals: 12. },
+			network: Network::Custom,
 			bid: Bid {
 				para_id: 2000,
 				leases: (0, 0),
@@ -65,20 +130,52 @@ impl Hunter {
 				))),
 				upper_limit: 100_000_000_000_000,
 				increment: 1_000_000_000_000,
+				approval: None,
+			},
+			notification: Notification {
+				mail: None,
+				notifiers: Vec::new(),
+				templates: Templates::new(&Default::default()).unwrap(),
 			},
-			notification: Notification { mail: None, webhooks: Vec::new() },
+			journal: None,
+			audit: None,
+			api: None,
+			bot: None,
+			light_client: None,
+			supervision: Supervision::default(),
+			naming: Naming::default(),
+			hash: String::new(),
 		};
-		let client = Self::ws_connect(&configuration.node_endpoint).await.unwrap();
-		let node = OnlineClient::from_rpc_client(RpcClient::new(client.clone())).await.unwrap();
+		let endpoints = Endpoint::connect_all(&configuration).await.unwrap();
+		let (token, ss58_prefix) =
+			endpoints[0].chain_properties(&configuration.network).await.unwrap();
+
+		let metrics = Arc::<Metrics>::default();
+
+		sp_core::crypto::set_default_ss58_version(ss58_prefix.into());
 
 		Self {
-			configuration,
 			http: util::http_json_client(),
-			_ws_connection: client,
-			node,
+			deliveries: DeliveryQueue::spawn(util::http_json_client(), metrics.clone()),
+			throttles: Throttles::default(),
+			incidents: Mutex::default(),
+			rpc: endpoints[0].rpc.clone(),
+			node: endpoints[0].node.clone(),
+			endpoints,
+			active: 0,
+			token,
+			names: Names::default(),
+			configuration,
 			auction_ending_period: 0,
 			auction_sample_length: 0,
 			bidder: AccountId::default(),
+			journal: None,
+			audit: None,
+			status: SharedStatus::default(),
+			metrics,
+			commands: Commands::default(),
+			paused: false,
+			overrides: HashSet::new(),
 		}
 	}
 
@@ -102,8 +199,9 @@ impl Hunter {
 		winners.iter().any(|w| self.is_bidder(&w.who, w.para_id))
 	}
 
-	pub async fn start(&mut self) -> Result<()> {
-		let (mut state, mut block_stream) = self.initialize().await?;
+	/// Hunt with the state, which is carried over the restarts of the supervisor.
+	async fn start(&mut self, state: &mut State) -> Result<()> {
+		let mut block_stream = self.initialize(state).await?;
 
 		loop {
 			if state.has_bid {
@@ -111,169 +209,132 @@ impl Hunter {
 
 				state.has_bid = false;
 
-				Self::next_block(&mut block_stream).await?;
+				block_stream.next().await?;
 			}
 
-			let block = Self::next_block(&mut block_stream).await?;
+			let (i, block) = block_stream.next().await?;
+
+			self.activate(i);
 
 			state.block_height = block.number();
 			state.block_hash = block.hash();
 
 			tracing::info!("block(#{}, {:?})", state.block_height, state.block_hash);
 
-			self.update(&mut state).await?;
-			self.hunt(&mut state).await?;
-		}
-	}
+			self.backfill_bid_index(state, &block).await;
 
-	pub fn ws_is_connected(&self) -> bool {
-		self._ws_connection.is_connected()
-	}
+			let events = self.auction_events_of(&block).await?;
+
+			self.apply_commands(state).await?;
+			self.analyze_events(state, &events).await?;
+			self.update(state, &events).await?;
+			self.hunt(state).await?;
+			self.notify_digests(state);
 
-	pub async fn ws_reconnect(&mut self, tried: &mut bool) -> Result<()> {
-		if *tried {
-			thread::sleep(Duration::from_secs(5));
+			self.report(state);
+			self.measure(state).await;
+
+			if let Some(j) = &mut self.journal {
+				if let Err(e) = j.write(&mem::take(&mut state.records)) {
+					tracing::error!("failed to write the journal due to error({e})");
+				}
+			} else {
+				state.records.clear();
+			}
 		}
+	}
 
-		*tried = true;
+	async fn initialize(&mut self, state: &mut State) -> Result<BlockStream> {
+		self.auction_ending_period = self.auction_ending_period()?;
+		self.auction_sample_length = self.auction_sample_length()?;
 
-		let client = Self::ws_connect(&self.configuration.node_endpoint).await?;
+		let mut block_stream = self.subscribe_blocks().await?;
+		let (i, block) = block_stream.next().await?;
+		let block_hash = block.hash();
 
-		self.node = OnlineClient::from_rpc_client(RpcClient::new(client.clone())).await?;
-		self._ws_connection = client;
+		self.activate(i);
 
-		Ok(())
-	}
+		self.check(&block_hash).await?;
 
-	async fn ws_connect(uri: &str) -> Result<Arc<WsClient>> {
-		let (tx, rx) = WsTransportClientBuilder::default().build(uri.parse()?).await?;
+		self.bidder = self.bidder_at(&block_hash).await?;
 
-		Ok(Arc::new(WsClientBuilder::default().build_with_tokio(tx, rx)))
-	}
+		let auction = self.auction_at(&block_hash).await?;
+
+		// Keep the state of the auction over a restart, such as our bid and the pending proposal.
+		// If the auction has been closed meanwhile, the next update closes it with the kept one.
+		if auction.is_some()
+			&& auction.as_ref().map(|a| a.index) != state.auction.as_ref().map(|a| a.index)
+		{
+			*state = State::default();
+			state.auction_is_open = true;
+		}
+		if auction.is_some() {
+			state.auction = auction;
+		}
 
-	async fn initialize(&mut self) -> Result<(State, BlockStream)> {
-		self.auction_ending_period = self.auction_ending_period().await?;
-		self.auction_sample_length = self.auction_sample_length().await?;
+		state.has_bid = false;
+		state.bid_index = BidIndex::load(&self.node.genesis_hash())?;
 
-		let mut state = State::default();
-		let mut block_stream = match self.configuration.block_subscription_mode {
-			BlockSubscriptionMode::Best => self.node.blocks().subscribe_best().await?,
-			BlockSubscriptionMode::Finalized => self.node.blocks().subscribe_finalized().await?,
-		};
-		let block_hash = Self::next_block(&mut block_stream).await?.hash();
+		// Bound the scan, the blocks are queued up meanwhile.
+		self.sync_bid_index(&mut state.bid_index, block_hash, Some(BID_INDEX_SCAN_WINDOW)).await?;
 
-		self.check(&block_hash).await?;
+		Ok(block_stream)
+	}
 
-		self.bidder = if self.is_self_funded() {
+	async fn bidder_at(&self, block_hash: &H256) -> Result<AccountId> {
+		Ok(if self.is_self_funded() {
 			self.configuration.bid.real
 		} else {
-			util::crowdloan_id_of(self.fund_index_at(&block_hash).await?.unwrap_or_else(|| {
-				panic!(
+			util::crowdloan_id_of(self.fund_index_at(block_hash).await?.ok_or_else(|| {
+				Error::Configuration(format!(
 					"no existing crowdloan found for parachain({})",
 					self.configuration.bid.para_id
-				)
-			}))
-		};
-		state.auction = self.auction_at(&block_hash).await?;
-		state.auction_is_open = state.auction.is_some();
-
-		Ok((state, block_stream))
-	}
-
-	// TODO: bug from clippy
-	#[allow(clippy::needless_pass_by_ref_mut)]
-	async fn next_block(block_stream: &mut BlockStream) -> Result<Block> {
-		Ok(block_stream.next().await.ok_or(anyhow::anyhow!("failed to get the next block"))??)
+				))
+			})?)
+		})
 	}
 
 	async fn check(&self, block_hash: &H256) -> Result<()> {
 		tracing::info!("############################################################");
+		self.configuration.node_endpoints.iter().for_each(|u| {
+			tracing::info!("node endpoint({u})");
+		});
 
-		{
-			let uri = &self.configuration.node_endpoint;
-
-			if util::check_ws_uri(uri) {
-				tracing::info!("node endpoint({uri})");
-			} else {
-				panic!("invalid node endpoint({uri})");
-			}
-		}
-
-		{
-			let uri = &self.configuration.graphql_endpoint;
-
-			if util::check_http_uri(uri) {
-				tracing::info!("graphql endpoint({uri})");
-			} else {
-				panic!("invalid graphql endpoint({uri})");
-			}
+		if let Some(uri) = &self.configuration.graphql_endpoint {
+			tracing::info!("graphql endpoint({uri}, {:?})", self.configuration.graphql_schema);
 		}
 
 		tracing::info!("bid");
-		tracing::info!("  hunting a slot for parachain({})", self.configuration.bid.para_id);
+		tracing::info!(
+			"  hunting a slot for parachain({})",
+			self.names.para(self.configuration.bid.para_id)
+		);
 		tracing::info!("  watch-only({})", self.watch_only());
 
 		if !self.watch_only() {
-			const E_DELEGATE: &str = "no delegate(`ProxyType::All` or `ProxyType::Auction`) found, please check your configurations";
-
 			tracing::info!("  funding type({})", self.configuration.bid.r#type);
-
-			let real = &self.configuration.bid.real;
-			let delegate = self.configuration.bid.delegate.account_id();
-
-			tracing::info!("  real account({})", array_bytes::bytes2hex("0x", real));
-			tracing::info!("  proxy delegate({})", array_bytes::bytes2hex("0x", delegate.0));
-
-			let ValueDef::Variant(v) = self
-				.proxies_at(block_hash, real)
-				.await?
-				.expect(E_DELEGATE)
-				.into_iter()
-				.find(|p| p.delegate.r#type == delegate.0)
-				.expect(E_DELEGATE)
-				.proxy_type
-				.value
-			else {
-				panic!("{E_DELEGATE}")
-			};
-
-			if !matches!(v.name.as_str(), "All" | "Auction") {
-				panic!("{E_DELEGATE}")
-			}
-
-			tracing::info!("  proxy type({})", v.name);
+			tracing::info!("  real account({})", self.names.account(&self.configuration.bid.real));
 			tracing::info!(
-				"  upper limit {}",
-				self.configuration.token.fmt(self.configuration.bid.upper_limit)
-			);
-
-			let increment = self.configuration.bid.increment;
-
-			tracing::info!("  increment {}", self.configuration.token.fmt(increment));
-
-			assert!(
-				increment as f64 / 10_f64.powf(self.configuration.token.decimals) >= 1.,
-				"increment should be at least {}(1)",
-				self.configuration.token.symbol
+				"  proxy delegate({})",
+				util::ss58(&self.configuration.bid.delegate.account_id().0)
 			);
+			tracing::info!("  upper limit {}", self.token.fmt(self.configuration.bid.upper_limit));
+			tracing::info!("  increment {}", self.token.fmt(self.configuration.bid.increment));
 		}
 
 		tracing::info!("notification");
-		tracing::info!("  webhooks");
+		tracing::info!("  notifiers");
 
-		self.configuration.notification.webhooks.iter().for_each(|u| {
-			tracing::info!("    uri({})", u);
-		});
+		self.configuration.notification.notifiers.iter().for_each(
+			|Channel { notifier: n, .. }| {
+				tracing::info!("    {}({})", n.name(), n.target());
+			},
+		);
 
 		if let Some(m) = &self.configuration.notification.mail {
 			tracing::info!("  mail");
 			tracing::info!("    sender({})", m.sender.username.email);
-
-			if util::check_smtp_uri(&m.sender.smtp) {
-				tracing::info!("    smtp({})", m.sender.smtp);
-			} else {
-				panic!("invalid smtp({})", m.sender.smtp);
-			}
+			tracing::info!("    smtp({})", m.sender.smtp);
 
 			for to in &m.receivers {
 				tracing::info!("    receiver({})", to.email);
@@ -282,39 +343,204 @@ impl Hunter {
 
 		tracing::info!("############################################################");
 
+		let diagnosis = self.diagnose(block_hash, &diagnosis::Diagnosis::default()).await?;
+
+		diagnosis.warnings().for_each(|f| tracing::warn!("{}: {}", f.item, f.message));
+		diagnosis.problems().for_each(|f| tracing::error!("{}: {}", f.item, f.message));
+		diagnosis.into_result()
+	}
+
+	async fn analyze_events(
+		&self,
+		state: &mut State,
+		events: &[Phased<AuctionEvent>],
+	) -> Result<()> {
+		let mut indexed = false;
+
+		for Phased { extrinsic_index, event } in events {
+			let at = if let Some(i) = extrinsic_index {
+				format!("extrinsic(#{}-{i})", state.block_height)
+			} else {
+				format!("block(#{})", state.block_height)
+			};
+
+			match event {
+				AuctionEvent::AuctionStarted(e) => {
+					tracing::info!(
+						"  auction(#{}) for lease period(#{}) has been started at {at}",
+						e.auction_index,
+						e.lease_period
+					);
+
+					state.bid_index.reset_to(e.auction_index);
+					state.bid_index.complete = true;
+					indexed = true;
+				},
+				AuctionEvent::AuctionClosed(e) =>
+					tracing::info!("  auction(#{}) has been closed at {at}", e.auction_index),
+				AuctionEvent::Reserved(e) => tracing::info!(
+					"  bidder({}) has reserved extra {} at {at}, total {}",
+					self.names.account(&e.bidder),
+					self.token.fmt(e.extra_reserved),
+					self.token.fmt(e.total_amount)
+				),
+				AuctionEvent::Unreserved(e) => tracing::info!(
+					"  bidder({}) has unreserved {} at {at}",
+					self.names.account(&e.bidder),
+					self.token.fmt(e.amount)
+				),
+				AuctionEvent::BidAccepted(e) => {
+					let leases = (e.first_slot, e.last_slot);
+
+					state.bid_index.insert(state.block_height, e);
+					indexed = true;
+
+					self.resolve_identities([&e.bidder]).await;
+
+					if self.is_bidder(&e.bidder, e.para_id) {
+						tracing::info!(
+							"  our bid {} for lease(s)[#{}, #{}] has been accepted at {at}",
+							self.token.fmt(e.amount),
+							e.first_slot,
+							e.last_slot
+						);
+
+						state.bid_amount = e.amount;
+					} else if util::ranges_are_intersecting(&leases, &self.configuration.bid.leases)
+					{
+						let n = format!(
+							"bidder({}, {}) has bid {} for lease(s)[#{}, #{}] at {at}",
+							self.names.account(&e.bidder),
+							self.names.para(e.para_id),
+							self.token.fmt(e.amount),
+							e.first_slot,
+							e.last_slot
+						);
+
+						tracing::warn!("  {n}");
+
+						self.notify(&self.context(state, NotificationEvent::Outbid(e.clone()), n));
+					} else {
+						tracing::info!(
+							"  bidder({}, {}) has bid {} for lease(s)[#{}, #{}] at {at}",
+							self.names.account(&e.bidder),
+							self.names.para(e.para_id),
+							self.token.fmt(e.amount),
+							e.first_slot,
+							e.last_slot
+						);
+					}
+				},
+				AuctionEvent::WinningOffset(e) => tracing::info!(
+					"  auction(#{}) has selected block(#{}) as the winning offset at {at}",
+					e.auction_index,
+					e.block_number
+				),
+				AuctionEvent::Leased(e) => {
+					let n = format!(
+						"parachain({}) has leased period(s)[#{}, #{}] with {} at {at}",
+						self.names.para(e.para_id),
+						e.period_begin,
+						e.period_begin + e.period_count.saturating_sub(1),
+						self.token.fmt(e.total_amount)
+					);
+
+					tracing::info!("  {n}");
+
+					if e.para_id == self.configuration.bid.para_id {
+						self.notify(&self.context(state, NotificationEvent::Leased(e.clone()), n));
+					}
+				},
+			}
+		}
+
+		if state.bid_index.complete {
+			state.bid_index.synced_to = state.block_height;
+		}
+		if indexed {
+			if let Err(e) = state.bid_index.save() {
+				tracing::warn!("failed to save the bid index due to error({e})");
+			}
+		}
+
 		Ok(())
 	}
 
-	async fn update(&self, state: &mut State) -> Result<()> {
+	/// Index the blocks between the last indexed one and this one, e.g. skipped after tendering or
+	/// dropped by the block racing, so `synced_to` never moves over an unindexed block.
+	async fn backfill_bid_index(&self, state: &mut State, block: &Block) {
+		let index = &mut state.bid_index;
+
+		if !index.complete || block.number() <= index.synced_to + 1 {
+			return;
+		}
+
+		tracing::info!(
+			"  backfill the bid index of block(s)[#{}, #{}]",
+			index.synced_to + 1,
+			block.number() - 1
+		);
+
+		if let Err(e) = self.sync_bid_index(index, block.header().parent_hash, None).await {
+			tracing::warn!("failed to backfill the bid index due to error({e})");
+
+			// Otherwise, this block would move `synced_to` over the gap.
+			index.complete = false;
+		}
+	}
+
+	async fn update(&self, state: &mut State, events: &[Phased<AuctionEvent>]) -> Result<()> {
 		let previous_auction = {
-			let auction = self.auction_at(&state.block_hash).await?;
+			// Prefer the events of this block, fall back to the storage otherwise.
+			let auction = if let Some(e) = events.iter().find_map(|e| match &e.event {
+				AuctionEvent::AuctionStarted(e) => Some(e),
+				_ => None,
+			}) {
+				Some(AuctionDetail::from(e))
+			} else if events.iter().any(|e| matches!(e.event, AuctionEvent::AuctionClosed(_))) {
+				None
+			} else {
+				self.auction_at(&state.block_hash).await?
+			};
 
 			mem::replace(&mut state.auction, auction)
 		};
 
-		state.auction_is_open = match (state.auction_is_open, state.auction.is_some()) {
+		state.auction_is_open = match (state.auction_is_open, state.auction.clone()) {
 			// An auction has just been opened.
-			(false, true) => {
-				let a = state.auction.as_ref().expect("`state.auction` must be some");
-
-				self.notify_mail(a, "auction has just been started");
-				self.notify_webhook(a, "auction has just been started").await;
+			(false, Some(a)) => {
+				self.notify(&self.context(
+					state,
+					NotificationEvent::AuctionStarted(a),
+					"auction has just been started",
+				));
+				self.check_balance(state).await?;
 
 				true
 			},
 			// An auction has just been closed.
-			(true, false) => {
-				let a = previous_auction.expect("`previous_auction` must be some");
+			(true, None) => {
+				// The supervisor restarts the hunter with a consistent state.
+				let a = previous_auction.ok_or_else(|| {
+					Error::Transient("no previous auction found for the closed auction".into())
+				})?;
+				let c = Context::new(
+					NotificationEvent::AuctionClosed(a.clone()),
+					"auction has just been closed",
+				)
+				.block(state.block_height, state.block_hash)
+				.auction(&a, 0);
 
-				self.notify_mail(&a, "auction has just been closed");
-				self.notify_webhook(&a, "auction has just been closed").await;
+				self.notify(&c);
+				self.resolve(Condition::BidFailed);
+				self.resolve(Condition::Unaffordable);
 
-				*state = State::default();
+				*state = State { bid_index: mem::take(&mut state.bid_index), ..Default::default() };
 
 				false
 			},
 			// Still in/not in an auction.
-			(_, is_some) => is_some,
+			(_, auction) => auction.is_some(),
 		};
 
 		Ok(())
@@ -323,7 +549,7 @@ impl Hunter {
 	async fn hunt(&self, state: &mut State) -> Result<()> {
 		let Some(auction) = &state.auction else { return Ok(()) };
 
-		self.check_leases(auction.first_lease_period);
+		self.check_leases(auction.first_lease_period)?;
 
 		let end_at = auction.ending_period_start_at + self.auction_ending_period;
 
@@ -343,13 +569,17 @@ impl Hunter {
 		self.analyze_winners(state).await
 	}
 
-	fn check_leases(&self, first_lease_period: u32) {
+	fn check_leases(&self, first_lease_period: u32) -> Result<()> {
 		let a @ (first, last) = util::range_of(first_lease_period);
 		let b @ (c_first, c_last) = self.configuration.bid.leases;
 
 		if !util::check_leases(&a, &b) {
-			panic!("invalid leases configuration, available range(#{first}, #{last}) but found range(#{c_first}, #{c_last})")
+			Err(Error::Configuration(format!(
+				"leases, available range(#{first}, #{last}) but found range(#{c_first}, #{c_last})"
+			)))?;
 		}
+
+		Ok(())
 	}
 
 	async fn analyze_bidders(&self, state: &mut State) -> Result<bool> {
@@ -357,7 +587,7 @@ impl Hunter {
 
 		tracing::info!("    bidders");
 
-		let bidders = self.bidders_at(&state.block_hash).await?;
+		let bidders = self.bidders_at(&state.block_hash, &state.bid_index).await?;
 
 		if bidders.is_empty() {
 			tracing::info!("      no bidders were found");
@@ -368,11 +598,20 @@ impl Hunter {
 			return Ok(false);
 		}
 
+		self.resolve_identities(bidders.iter().map(|b| &b.who)).await;
+
 		bidders.into_iter().for_each(|b| {
-			tracing::info!("      {}", b.fmt(&self.configuration.token));
+			tracing::info!("      {}", b.fmt(&self.token, &self.names));
+
+			state.records.push(Record::of_bidder(
+				auction.index,
+				state.block_height,
+				state.block_hash,
+				&b,
+			));
 
 			if let Some(l) = &b.last_accepted_bid {
-				tracing::info!("        last accepted bid is {}", l.fmt(&self.configuration.token));
+				tracing::info!("        last accepted bid is {}", l.fmt(&self.token));
 
 				if self.is_bidder(&b.who, b.para_id) {
 					state.bid_amount = l.amount;
@@ -388,10 +627,16 @@ impl Hunter {
 
 		tracing::info!("    winning");
 
-		state.winning = self
+		let Some(winning) = self
 			.winning_at(&state.block_hash, state.block_height, auction.ending_period_start_at)
 			.await?
-			.expect("winning must be some if bidders is not empty");
+		else {
+			tracing::warn!("      no winning was found while there are bidders");
+
+			return Ok(false);
+		};
+
+		state.winning = winning;
 
 		if state.winning.0.iter().all(Option::is_none) {
 			tracing::info!("      no winning has been calculated yet");
@@ -400,11 +645,19 @@ impl Hunter {
 			return Ok(false);
 		}
 
+		self.resolve_identities(state.winning.0.iter().flatten().map(|w| &w.who)).await;
+
 		state
 			.winning
-			.fmt(&self.configuration.token, auction.first_lease_period)
+			.fmt(&self.token, &self.names, auction.first_lease_period)
 			.into_iter()
 			.for_each(|w| tracing::info!("      {w}"));
+		state.records.extend(Record::of_winning(
+			auction.index,
+			state.block_height,
+			state.block_hash,
+			&state.winning,
+		));
 
 		Ok(true)
 	}
@@ -415,33 +668,89 @@ impl Hunter {
 		tracing::info!("    winner(s)");
 
 		let (winners, threshold) = state.winning.result();
-		let notification = winners
+
+		state.records.extend(Record::of_winners(
+			auction.index,
+			state.block_height,
+			state.block_hash,
+			&winners,
+			threshold,
+		));
+		let winner_contexts = winners
 			.iter()
 			.map(|w| {
-				let n = w.fmt(&self.configuration.token, auction.first_lease_period);
+				tracing::info!(
+					"      {}",
+					w.fmt(&self.token, &self.names, auction.first_lease_period)
+				);
+
+				WinnerContext {
+					bidder: self.names.account(&w.who),
+					parachain: self.names.para(w.para_id),
+					leases: format!(
+						"#{}-#{}",
+						auction.first_lease_period + w.leases.0,
+						auction.first_lease_period + w.leases.1
+					),
+					amount: self.token.fmt(w.value),
+				}
+			})
+			.collect::<Vec<_>>();
+		let table = util::markdown_table(
+			&["bidder", "parachain", "leases", "amount"],
+			&winner_contexts
+				.iter()
+				.map(|w| {
+					vec![w.bidder.clone(), w.parachain.clone(), w.leases.clone(), w.amount.clone()]
+				})
+				.collect::<Vec<_>>(),
+		);
+
+		let is_winner = self.is_winner(&winners);
+
+		// Only notify the changes, our winning status is derived from the winners.
+		if state.notified_winners.as_ref() != Some(&(winners.clone(), threshold)) {
+			self.notify(
+				&self
+					.context(
+						state,
+						NotificationEvent::Winners { winners: winners.clone(), threshold },
+						format!(
+							"winner(s) at block(#{}, {:?})\n{table}",
+							state.block_height, state.block_hash
+						),
+					)
+					.winners(winner_contexts)
+					.threshold(self.token.fmt(threshold)),
+			);
 
-				tracing::info!("      {n}");
+			state.notified_winners = Some((winners.clone(), threshold));
+		}
 
-				n
-			})
-			.collect::<Vec<_>>()
-			.join(",");
-
-		self.notify_webhook(
-			&serde_json::json!({
-				"block": {
-					"height": state.block_height,
-					"hash": state.block_hash,
-				},
-				"winning": state.winning.0.as_slice(),
-				"winners": winners,
+		if state.is_winner != Some(is_winner) {
+			let n = if is_winner {
+				"we are winning the auction now"
+			} else {
+				"we are not winning the auction now"
+			};
+
+			tracing::info!("    {n}");
 
-			}),
-			&format!("at block(#{}, {:?})\n{notification}", state.block_hash, state.block_height),
-		)
-		.await;
+			state.is_winner = Some(is_winner);
 
-		if !self.is_winner(&winners) {
+			self.notify(&self.context(
+				state,
+				NotificationEvent::WinningStatusChanged { winning: is_winner },
+				n,
+			));
+
+			if is_winner {
+				self.resolve(Condition::BidFailed);
+				self.resolve(Condition::Unaffordable);
+			}
+		}
+
+		if !is_winner {
 			let leases = (
 				self.configuration.bid.leases.0 - auction.first_lease_period,
 				self.configuration.bid.leases.1 - auction.first_lease_period,
@@ -456,115 +765,292 @@ impl Hunter {
 	}
 
 	async fn try_tender(&self, state: &mut State, auction_index: u32, bid: Balance) -> Result<()> {
-		if self.watch_only() {
+		if self.paused {
+			let notification =
+				format!("    tendering has been paused, skip {}", self.token.fmt(bid));
+
+			tracing::warn!("{notification}");
+
+			self.audit(state, AuditAction::Skip, bid, &notification, None, None);
+
+			self.notify(
+				&self
+					.context(
+						state,
+						NotificationEvent::TenderSkipped { amount: bid },
+						notification.trim_start(),
+					)
+					.bid(self.token.fmt(bid)),
+			);
+
+			Ok(())
+		} else if self.watch_only() {
 			fn log(mode: &str, bid: String) -> String {
 				format!("    slothunter is running under the watch-only mode, {mode} {bid} manually to win")
 			}
 
 			let notification = if self.is_self_funded() {
-				log("bid", self.configuration.token.fmt(bid))
+				log("bid", self.token.fmt(bid))
 			} else {
-				log("contribute", self.configuration.token.fmt(bid - state.bid_amount))
+				log("contribute", self.token.fmt(bid - state.bid_amount))
 			};
 
 			tracing::warn!("{notification}");
 
-			self.notify_webhook(&None::<()>, &notification).await;
+			state.records.push(
+				Record::new(
+					auction_index,
+					state.block_height,
+					state.block_hash,
+					RecordKind::Action,
+				)
+				.amount(bid)
+				.note(notification.trim_start()),
+			);
+			self.audit(state, AuditAction::Skip, bid, &notification, None, None);
+
+			self.notify(
+				&self
+					.context(
+						state,
+						NotificationEvent::TenderSkipped { amount: bid },
+						notification.trim_start(),
+					)
+					.bid(self.token.fmt(bid)),
+			);
+
+			Ok(())
+		} else if let Some(p) =
+			self.approval_policy_of(bid).filter(|_| !state.is_approved(auction_index, bid))
+		{
+			self.propose(state, auction_index, bid, p);
 
 			Ok(())
 		} else {
-			fn log(mode: &str, bid: String, upper_limit: String) -> String {
-				format!("    skip {mode} {bid} because it exceeds the upper limit {upper_limit}")
-			}
+			self.tender(state, auction_index, bid).await
+		}
+	}
 
-			let notification;
-			let unaffordable;
+	async fn tender(&self, state: &mut State, auction_index: u32, bid: Balance) -> Result<()> {
+		fn log(mode: &str, bid: String, upper_limit: String) -> String {
+			format!("    skip {mode} {bid} because it exceeds the upper limit {upper_limit}")
+		}
 
-			if self.is_self_funded() {
-				if self.can_spend(bid) {
-					if let Err(e) = self.bid(auction_index, bid).await? {
-						let n = format!("    bid failed due to error({e:?})");
+		let notification;
+		let unaffordable;
+		let event;
+		let mut extrinsic = None;
 
-						tracing::error!("{n}");
+		if self.is_self_funded() {
+			if self.can_spend(bid) {
+				self.metrics.tender_attempts.inc();
 
-						state.has_bid = false;
-						state.retries += 1;
-						unaffordable = false;
-						notification = n.trim_start_matches(' ').to_string();
-					} else {
-						let n = format!("    bid with {}", self.configuration.token.fmt(bid));
+				let (hash, result) = self
+					.bid(auction_index, bid)
+					.await
+					.inspect_err(|_| self.metrics.tender_failures.inc())?;
+				let result = self.audit_unwatched(state, AuditAction::Bid, bid, hash, result)?;
 
-						tracing::info!("{n}");
+				extrinsic = Some((hash, result.clone()));
 
-						state.has_bid = true;
-						state.retries = 0;
-						unaffordable = false;
-						notification = n.trim_start_matches(' ').to_string();
-					}
-				} else {
-					let n = log(
-						"bidding",
-						self.configuration.token.fmt(bid),
-						self.configuration.token.fmt(self.configuration.bid.upper_limit),
-					);
+				if let Err(e) = result {
+					let n = format!("    bid failed due to error({e:?})");
 
-					tracing::warn!("{n}");
+					event = NotificationEvent::BidFailed { amount: bid, error: format!("{e:?}") };
+
+					tracing::error!("{n}");
+
+					self.metrics.tender_failures.inc();
 
 					state.has_bid = false;
+					state.retries += 1;
+					unaffordable = false;
+					notification = n.trim_start_matches(' ').to_string();
+				} else {
+					let n = format!("    bid with {}", self.token.fmt(bid));
+
+					event = NotificationEvent::BidSubmitted { amount: bid };
+
+					tracing::info!("{n}");
+
+					self.metrics.tender_successes.inc();
+
+					state.has_bid = true;
 					state.retries = 0;
-					unaffordable = true;
+					unaffordable = false;
 					notification = n.trim_start_matches(' ').to_string();
 				}
 			} else {
-				let bid = bid - state.bid_amount;
+				let n = log(
+					"bidding",
+					self.token.fmt(bid),
+					self.token.fmt(self.configuration.bid.upper_limit),
+				);
 
-				if self.can_spend(bid) {
-					if let Err(e) = self.contribute(bid).await? {
-						let n = format!("    contribute failed due to error({e:?})");
+				tracing::warn!("{n}");
 
-						tracing::error!("{n}");
+				event = NotificationEvent::Unaffordable {
+					amount: bid,
+					upper_limit: self.configuration.bid.upper_limit,
+				};
 
-						state.has_bid = false;
-						state.retries += 1;
-						unaffordable = false;
-						notification = n.trim_start_matches(' ').to_string();
-					} else {
-						let n =
-							format!("    contribute with {}", self.configuration.token.fmt(bid));
+				state.has_bid = false;
+				state.retries = 0;
+				unaffordable = true;
+				notification = n.trim_start_matches(' ').to_string();
+			}
+		} else {
+			let bid = bid - state.bid_amount;
 
-						tracing::info!("{n}");
+			if self.can_spend(bid) {
+				self.metrics.tender_attempts.inc();
 
-						state.has_bid = true;
-						state.retries = 0;
-						unaffordable = false;
-						notification = n.trim_start_matches(' ').to_string();
-					}
-				} else {
-					let n = log(
-						"contributing",
-						self.configuration.token.fmt(bid),
-						self.configuration.token.fmt(self.configuration.bid.upper_limit),
-					);
+				let (hash, result) = self
+					.contribute(bid)
+					.await
+					.inspect_err(|_| self.metrics.tender_failures.inc())?;
+				let result =
+					self.audit_unwatched(state, AuditAction::Contribute, bid, hash, result)?;
+
+				extrinsic = Some((hash, result.clone()));
 
-					tracing::warn!("{n}");
+				if let Err(e) = result {
+					let n = format!("    contribute failed due to error({e:?})");
+
+					event = NotificationEvent::BidFailed { amount: bid, error: format!("{e:?}") };
+
+					tracing::error!("{n}");
+
+					self.metrics.tender_failures.inc();
 
 					state.has_bid = false;
+					state.retries += 1;
+					unaffordable = false;
+					notification = n.trim_start_matches(' ').to_string();
+				} else {
+					let n = format!("    contribute with {}", self.token.fmt(bid));
+
+					event = NotificationEvent::BidSubmitted { amount: bid };
+
+					tracing::info!("{n}");
+
+					self.metrics.tender_successes.inc();
+
+					state.has_bid = true;
 					state.retries = 0;
-					unaffordable = true;
+					unaffordable = false;
 					notification = n.trim_start_matches(' ').to_string();
 				}
-			}
+			} else {
+				let n = log(
+					"contributing",
+					self.token.fmt(bid),
+					self.token.fmt(self.configuration.bid.upper_limit),
+				);
 
-			if state.retries < 5 && !state.unaffordable {
-				self.notify_mail(&None::<()>, &notification);
+				tracing::warn!("{n}");
+
+				event = NotificationEvent::Unaffordable {
+					amount: bid,
+					upper_limit: self.configuration.bid.upper_limit,
+				};
+
+				state.has_bid = false;
+				state.retries = 0;
+				unaffordable = true;
+				notification = n.trim_start_matches(' ').to_string();
 			}
+		}
+
+		state.records.push(
+			Record::new(auction_index, state.block_height, state.block_hash, RecordKind::Action)
+				.who(&self.bidder, self.configuration.bid.para_id)
+				.leases(self.configuration.bid.leases)
+				.amount(bid)
+				.note(&notification),
+		);
+
+		let action = match (&extrinsic, self.is_self_funded()) {
+			(None, _) => AuditAction::Skip,
+			(_, true) => AuditAction::Bid,
+			(_, false) => AuditAction::Contribute,
+		};
 
-			self.notify_webhook(&None::<()>, &notification).await;
+		let (extrinsic_hash, dispatch_result) = extrinsic.unzip();
 
-			state.unaffordable = unaffordable;
+		self.audit(state, action, bid, &notification, extrinsic_hash, dispatch_result);
 
-			Ok(())
+		let submitted = matches!(event, NotificationEvent::BidSubmitted { .. });
+		// Avoid flooding the channels with the repeated failures.
+		let repeated = match event {
+			NotificationEvent::BidFailed { .. } => state.retries >= 5,
+			NotificationEvent::Unaffordable { .. } => state.unaffordable,
+			_ => false,
+		};
+
+		if !repeated {
+			self.notify(&self.context(state, event, notification).bid(self.token.fmt(bid)));
 		}
+
+		state.unaffordable = unaffordable;
+
+		if submitted {
+			self.resolve(Condition::BidFailed);
+			self.resolve(Condition::Unaffordable);
+		}
+
+		Ok(())
+	}
+}
+
+impl Hunter {
+	/// Notify if the real account can't afford the upper limit.
+	async fn check_balance(&self, state: &State) -> Result<()> {
+		if self.watch_only() || !self.is_self_funded() {
+			return Ok(());
+		}
+
+		let upper_limit = self.configuration.bid.upper_limit;
+		let free = self.free_balance_at(&state.block_hash, &self.configuration.bid.real).await?;
+
+		if free < upper_limit {
+			let n = format!(
+				"real account's free balance {} is lower than the upper limit {}",
+				self.token.fmt(free),
+				self.token.fmt(upper_limit)
+			);
+
+			tracing::warn!("{n}");
+
+			self.notify(&self.context(
+				state,
+				NotificationEvent::LowBalance { free, upper_limit },
+				n,
+			));
+		}
+
+		Ok(())
+	}
+
+	/// Build the notification context with the current block and auction.
+	fn context(
+		&self,
+		state: &State,
+		event: NotificationEvent,
+		message: impl Into<String>,
+	) -> Context {
+		let mut c = Context::new(event, message).block(state.block_height, state.block_hash);
+
+		if let Some(a) = &state.auction {
+			let end_at = a.ending_period_start_at + self.auction_ending_period;
+
+			c = c.auction(a, end_at.saturating_sub(state.block_height));
+		}
+		if state.bid_amount != 0 {
+			c = c.bid(self.token.fmt(state.bid_amount));
+		}
+
+		c
 	}
 }
 
@@ -575,8 +1061,27 @@ struct State {
 	auction: Option<AuctionDetail>,
 	auction_is_open: bool,
 	has_bid: bool,
+	/// Whether we were winning at the last analysis, `None` if not analyzed yet.
+	is_winner: Option<bool>,
+	/// Winners and threshold of the last winners notification.
+	notified_winners: Option<(Vec<Winner>, Balance)>,
 	bid_amount: Balance,
 	winning: Winning,
 	retries: u8,
 	unaffordable: bool,
+	bid_index: BidIndex,
+	records: Vec<Record>,
+	/// Bid which is waiting for the approval of the bot.
+	proposal: Option<Proposal>,
+	/// Amount of the last proposal, the same amount won't be proposed twice.
+	proposed: Option<Balance>,
+	/// Auction and ceiling of the last approved proposal, the bids up to the ceiling need no more
+	/// approvals.
+	approved: Option<(u32, Balance)>,
+}
+impl State {
+	/// Whether the bid is covered by the approved ceiling of the auction.
+	fn is_approved(&self, auction_index: u32, bid: Balance) -> bool {
+		self.approved.is_some_and(|(i, c)| i == auction_index && bid <= c)
+	}
 }
@@ -0,0 +1,406 @@
+// std
+use std::{
+	convert::Infallible,
+	net::SocketAddr,
+	sync::{Arc, RwLock},
+	time::{SystemTime, UNIX_EPOCH},
+};
+// crates.io
+use hyper::{
+	header::{AUTHORIZATION, CONTENT_TYPE},
+	service::{make_service_fn, service_fn},
+	Body, Method, Request, Response, Server, StatusCode,
+};
+use serde::Serialize;
+use subxt::config::polkadot::H256;
+use tokio::sync::mpsc::UnboundedSender;
+// slothunter
+use crate::hunter::*;
+
+pub type SharedStatus = Arc<RwLock<Status>>;
+
+/// A snapshot of the hunter, which will be updated on every block.
+#[derive(Debug, Default, Serialize)]
+pub struct Status {
+	pub block: BlockStatus,
+	pub state: StateStatus,
+	pub winners: WinnersStatus,
+	/// The minimum bid to win the configured leases.
+	pub minimum_bid_to_win: Option<Balance>,
+	pub limits: LimitsStatus,
+	pub health: HealthStatus,
+}
+#[derive(Debug, Default, Serialize)]
+pub struct BlockStatus {
+	pub height: BlockNumber,
+	pub hash: H256,
+}
+#[derive(Debug, Default, Serialize)]
+pub struct StateStatus {
+	pub auction: Option<AuctionDetail>,
+	pub auction_is_open: bool,
+	pub winning: Vec<Winner>,
+	pub bid_amount: Balance,
+	pub retries: u8,
+	pub unaffordable: bool,
+}
+#[derive(Debug, Default, Serialize)]
+pub struct WinnersStatus {
+	pub winners: Vec<Winner>,
+	pub threshold: Balance,
+}
+#[derive(Debug, Default, Serialize)]
+pub struct LimitsStatus {
+	pub para_id: ParaId,
+	pub leases: SlotRange,
+	pub watch_only: bool,
+	pub r#type: String,
+	pub upper_limit: Balance,
+	pub increment: Balance,
+}
+#[derive(Debug, Default, Serialize)]
+pub struct HealthStatus {
+	pub connected: bool,
+	/// UNIX timestamp in seconds of the last processed block.
+	pub last_block_at: u64,
+}
+
+fn json<S>(status: StatusCode, body: &S) -> Response<Body>
+where
+	S: Serialize,
+{
+	let mut response =
+		Response::new(Body::from(serde_json::to_vec(body).expect("json must be valid")));
+
+	*response.status_mut() = status;
+	response.headers_mut().insert(CONTENT_TYPE, "application/json".parse().unwrap());
+
+	response
+}
+
+#[derive(Clone, Debug)]
+pub struct ApiContext {
+	pub status: SharedStatus,
+	pub metrics: Arc<Metrics>,
+	/// Bearer token of the control endpoints, they will be disabled if this is `None`.
+	pub token: Option<String>,
+	/// Bearer token of each approver, keyed by the approver's name.
+	pub approver_tokens: HashMap<String, String>,
+	pub commands: UnboundedSender<Command>,
+	/// Slack app of the bot, its endpoints will be disabled if this is `None`.
+	pub slack: Option<Slack>,
+}
+impl ApiContext {
+	fn authorize(&self, request: &Request<Body>) -> Option<Caller> {
+		let bearer = request
+			.headers()
+			.get(AUTHORIZATION)
+			.and_then(|v| v.to_str().ok())
+			.and_then(|v| v.strip_prefix("Bearer "))?;
+
+		if self.token.as_ref().is_some_and(|t| util::constant_time_eq(bearer, t)) {
+			return Some(Caller::Operator);
+		}
+
+		self.approver_tokens
+			.iter()
+			.find(|(_, t)| util::constant_time_eq(bearer, t))
+			.map(|(name, _)| Caller::Approver(name.to_owned()))
+	}
+}
+
+/// Owner of the bearer token.
+#[derive(Debug, PartialEq, Eq)]
+enum Caller {
+	Operator,
+	Approver(String),
+}
+
+async fn control(context: &ApiContext, request: Request<Body>) -> Response<Body> {
+	let Some(caller) = context.authorize(&request) else {
+		return json(StatusCode::UNAUTHORIZED, &serde_json::json!({ "error": "unauthorized" }));
+	};
+
+	let body = match hyper::body::to_bytes(request.into_body()).await {
+		Ok(b) => b,
+		Err(e) =>
+			return json(StatusCode::BAD_REQUEST, &serde_json::json!({ "error": e.to_string() })),
+	};
+	let command = match serde_json::from_slice::<Command>(&body) {
+		Ok(c) => c,
+		Err(e) =>
+			return json(StatusCode::BAD_REQUEST, &serde_json::json!({ "error": e.to_string() })),
+	};
+	// Each approval is bound to its approver's own token, one token can't approve twice.
+	let command = match (caller, command) {
+		(Caller::Approver(name), Command::Approve { id, .. }) => Command::Approve { id, by: name },
+		(Caller::Approver(name), Command::Reject { id, .. }) => Command::Reject { id, by: name },
+		(Caller::Operator, Command::Approve { .. } | Command::Reject { .. }) =>
+			return json(
+				StatusCode::FORBIDDEN,
+				&serde_json::json!({ "error": "approvals require an approver token" }),
+			),
+		(Caller::Approver(_), _) =>
+			return json(
+				StatusCode::FORBIDDEN,
+				&serde_json::json!({ "error": "approver tokens can only approve or reject" }),
+			),
+		(Caller::Operator, c) => c,
+	};
+
+	tracing::info!("received control command({command:?})");
+
+	if context.commands.send(command).is_err() {
+		return json(
+			StatusCode::SERVICE_UNAVAILABLE,
+			&serde_json::json!({ "error": "hunter is not running" }),
+		);
+	}
+
+	// The command will be applied before processing the next block.
+	json(StatusCode::ACCEPTED, &serde_json::json!({ "accepted": true }))
+}
+
+async fn slack(context: &ApiContext, request: Request<Body>) -> Response<Body> {
+	let Some(slack) = &context.slack else {
+		return json(StatusCode::NOT_FOUND, &serde_json::json!({ "error": "not found" }));
+	};
+	let header =
+		|k| request.headers().get(k).and_then(|v| v.to_str().ok()).unwrap_or_default().to_owned();
+	let (timestamp, signature) = (header("X-Slack-Request-Timestamp"), header("X-Slack-Signature"));
+	let is_command = request.uri().path() == "/slack/commands";
+	let body = match hyper::body::to_bytes(request.into_body()).await {
+		Ok(b) => b,
+		Err(e) =>
+			return json(StatusCode::BAD_REQUEST, &serde_json::json!({ "error": e.to_string() })),
+	};
+	let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+
+	if !slack.verify(&timestamp, &body, &signature, now) {
+		return json(StatusCode::UNAUTHORIZED, &serde_json::json!({ "error": "unauthorized" }));
+	}
+
+	let reply = if is_command { slack.command(&body) } else { slack.interaction(&body) };
+
+	json(
+		StatusCode::OK,
+		&serde_json::json!({
+			"response_type": "in_channel",
+			"text": reply.unwrap_or_else(|| "usage: status | approve <id> | reject <id>".into()),
+		}),
+	)
+}
+
+async fn route(context: &ApiContext, request: Request<Body>) -> Response<Body> {
+	if (request.method(), request.uri().path()) == (&Method::POST, "/control") {
+		return control(context, request).await;
+	}
+	if request.method() == Method::POST
+		&& matches!(request.uri().path(), "/slack/commands" | "/slack/interactions")
+	{
+		return slack(context, request).await;
+	}
+	if (request.method(), request.uri().path()) == (&Method::GET, "/metrics") {
+		let mut response = Response::new(Body::from(context.metrics.encode()));
+
+		response.headers_mut().insert(CONTENT_TYPE, "text/plain; version=0.0.4".parse().unwrap());
+
+		return response;
+	}
+
+	let status = context.status.read().expect("lock must not be poisoned");
+
+	match (request.method(), request.uri().path()) {
+		(&Method::GET, "/status") => json(StatusCode::OK, &*status),
+		(&Method::GET, "/state") => json(StatusCode::OK, &status.state),
+		(&Method::GET, "/winners") => json(
+			StatusCode::OK,
+			&serde_json::json!({
+				"winners": status.winners.winners,
+				"threshold": status.winners.threshold,
+				"minimum_bid_to_win": status.minimum_bid_to_win,
+			}),
+		),
+		(&Method::GET, "/limits") => json(StatusCode::OK, &status.limits),
+		(&Method::GET, "/health") => json(
+			if status.health.connected { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE },
+			&status.health,
+		),
+		_ => json(StatusCode::NOT_FOUND, &serde_json::json!({ "error": "not found" })),
+	}
+}
+
+pub fn serve(address: SocketAddr, context: ApiContext) -> Result<()> {
+	let server = Server::try_bind(&address)?.serve(make_service_fn(move |_| {
+		let context = context.clone();
+
+		async move {
+			Ok::<_, Infallible>(service_fn(move |r| {
+				let context = context.clone();
+
+				async move { Ok::<_, Infallible>(route(&context, r).await) }
+			}))
+		}
+	}));
+
+	tracing::info!("api server is listening on {address}");
+
+	tokio::spawn(async move {
+		if let Err(e) = server.await {
+			tracing::error!("api server exited due to error({e})");
+		}
+	});
+
+	Ok(())
+}
+
+impl Hunter {
+	pub fn serve_api(&self) -> Result<()> {
+		if let Some(a) = &self.configuration.api {
+			serve(
+				a.address,
+				ApiContext {
+					status: self.status.clone(),
+					metrics: self.metrics.clone(),
+					token: a.token.clone(),
+					approver_tokens: a.approver_tokens.clone(),
+					commands: self.commands.sender.clone(),
+					slack: self.configuration.bot.as_ref().and_then(|b| match &b.chat {
+						Chat::Slack { signing_secret, .. } => Some(Slack {
+							signing_secret: signing_secret.clone(),
+							bot: self.bot_context(b),
+						}),
+						_ => None,
+					}),
+				},
+			)?;
+		}
+
+		Ok(())
+	}
+
+	pub fn report_disconnected(&self) {
+		self.status.write().expect("lock must not be poisoned").health.connected = false;
+	}
+
+	/// The minimum bid to win the configured leases, `None` if they are out of the auction's range.
+	pub(super) fn minimum_bid_to_win(&self, state: &State, threshold: Balance) -> Option<Balance> {
+		state.auction.as_ref().and_then(|a| {
+			let leases = self.configuration.bid.leases;
+
+			if util::check_leases(&util::range_of(a.first_lease_period), &leases) {
+				let leases = (leases.0 - a.first_lease_period, leases.1 - a.first_lease_period);
+
+				Some(state.winning.minimum_bid_to_win(&leases, threshold))
+			} else {
+				None
+			}
+		})
+	}
+
+	pub(super) fn report(&self, state: &State) {
+		let (winners, threshold) = state.winning.result();
+		let minimum_bid_to_win = self.minimum_bid_to_win(state, threshold);
+		let bid = &self.configuration.bid;
+		let mut status = self.status.write().expect("lock must not be poisoned");
+
+		*status = Status {
+			block: BlockStatus { height: state.block_height, hash: state.block_hash },
+			state: StateStatus {
+				auction: state.auction.clone(),
+				auction_is_open: state.auction_is_open,
+				winning: state.winning.0.iter().filter_map(Clone::clone).collect(),
+				bid_amount: state.bid_amount,
+				retries: state.retries,
+				unaffordable: state.unaffordable,
+			},
+			winners: WinnersStatus { winners, threshold },
+			minimum_bid_to_win,
+			limits: LimitsStatus {
+				para_id: bid.para_id,
+				leases: bid.leases,
+				watch_only: bid.watch_only,
+				r#type: bid.r#type.to_string(),
+				upper_limit: bid.upper_limit,
+				increment: bid.increment,
+			},
+			health: HealthStatus {
+				connected: true,
+				last_block_at: SystemTime::now()
+					.duration_since(UNIX_EPOCH)
+					.map(|d| d.as_secs())
+					.unwrap_or_default(),
+			},
+		};
+	}
+}
+#[tokio::test]
+async fn route_should_work() {
+	let commands = Commands::default();
+	let context = ApiContext {
+		status: SharedStatus::default(),
+		metrics: Arc::default(),
+		token: Some("secret".into()),
+		approver_tokens: [("alice", "alice-secret"), ("bob", "bob-secret")]
+			.into_iter()
+			.map(|(n, t)| (n.into(), t.into()))
+			.collect(),
+		commands: commands.sender.clone(),
+		slack: None,
+	};
+	let get = |path: &str| {
+		let context = context.clone();
+		let request = Request::get(path).body(Body::empty()).unwrap();
+
+		async move { route(&context, request).await.status() }
+	};
+	let post = |token: &str, body: &str| {
+		let context = context.clone();
+		let request = Request::post("/control")
+			.header(AUTHORIZATION, format!("Bearer {token}"))
+			.body(Body::from(body.to_owned()))
+			.unwrap();
+
+		async move { route(&context, request).await.status() }
+	};
+
+	assert_eq!(get("/status").await, StatusCode::OK);
+	assert_eq!(get("/state").await, StatusCode::OK);
+	assert_eq!(get("/winners").await, StatusCode::OK);
+	assert_eq!(get("/limits").await, StatusCode::OK);
+	assert_eq!(get("/health").await, StatusCode::SERVICE_UNAVAILABLE);
+	assert_eq!(get("/metrics").await, StatusCode::OK);
+	assert_eq!(get("/unknown").await, StatusCode::NOT_FOUND);
+	assert_eq!(
+		route(&context, Request::post("/slack/commands").body(Body::empty()).unwrap())
+			.await
+			.status(),
+		StatusCode::NOT_FOUND
+	);
+
+	context.status.write().unwrap().health.connected = true;
+
+	assert_eq!(get("/health").await, StatusCode::OK);
+
+	assert_eq!(post("wrong", r#"{"command":"pause"}"#).await, StatusCode::UNAUTHORIZED);
+	assert_eq!(post("secret", r#"{"command":"unknown"}"#).await, StatusCode::BAD_REQUEST);
+	assert_eq!(post("secret", r#"{"command":"pause"}"#).await, StatusCode::ACCEPTED);
+	assert_eq!(
+		post("secret", r#"{"command":"approve","id":7,"by":"alice"}"#).await,
+		StatusCode::FORBIDDEN
+	);
+	assert_eq!(post("alice-secret", r#"{"command":"pause"}"#).await, StatusCode::FORBIDDEN);
+	assert_eq!(
+		post("alice-secret", r#"{"command":"approve","id":7,"by":"bob"}"#).await,
+		StatusCode::ACCEPTED
+	);
+
+	let mut commands = commands;
+
+	assert!(matches!(commands.try_recv(), Some(Command::Pause)));
+	assert!(matches!(
+		commands.try_recv(),
+		Some(Command::Approve { id: 7, by }) if by == "alice"
+	));
+	assert!(commands.try_recv().is_none());
+}
@@ -0,0 +1,400 @@
+// std
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+// slothunter
+use crate::hunter::*;
+
+/// A bid which is waiting for the approvals.
+///
+/// The approvals cover the bids up to the ceiling, so a higher bid within the ceiling keeps the
+/// collected approvals instead of starting over.
+#[derive(Debug)]
+pub struct Proposal {
+	pub id: u64,
+	pub auction_index: u32,
+	/// The latest bid, which will be tendered once approved.
+	pub amount: Balance,
+	pub ceiling: Balance,
+	pub policy: ApprovalPolicy,
+	/// Distinct approvers who have approved.
+	pub approvals: Vec<String>,
+	pub expires_at: Instant,
+}
+impl Proposal {
+	pub fn new(
+		auction_index: u32,
+		amount: Balance,
+		ceiling: Balance,
+		policy: ApprovalPolicy,
+	) -> Self {
+		Self {
+			// Unique across the restarts, so the stale buttons can't approve a new proposal.
+			id: SystemTime::now()
+				.duration_since(UNIX_EPOCH)
+				.map(|d| d.as_millis() as _)
+				.unwrap_or_default(),
+			auction_index,
+			amount,
+			ceiling,
+			expires_at: Instant::now() + Duration::from_secs(policy.timeout),
+			policy,
+			approvals: Vec::new(),
+		}
+	}
+
+	pub fn is_expired(&self) -> bool {
+		Instant::now() >= self.expires_at
+	}
+
+	/// Move the proposal to the bid if it's within the ceiling, the collected approvals are kept.
+	///
+	/// Return `false` if the bid needs a new proposal.
+	pub fn rebid(&mut self, auction_index: u32, bid: Balance) -> bool {
+		if self.auction_index != auction_index || bid > self.ceiling || self.is_expired() {
+			return false;
+		}
+
+		self.amount = bid;
+
+		true
+	}
+
+	pub fn is_approver(&self, who: &str) -> bool {
+		self.policy.approvers.iter().any(|a| a == who)
+	}
+
+	/// Record the approval, return whether the proposal has enough approvals.
+	pub fn approve(&mut self, by: &str) -> bool {
+		if !self.approvals.iter().any(|a| a == by) {
+			self.approvals.push(by.into());
+		}
+
+		self.approvals.len() >= self.policy.required
+	}
+
+	pub fn progress(&self) -> String {
+		format!("{}/{}", self.approvals.len(), self.policy.required)
+	}
+}
+#[test]
+fn proposal_should_work() {
+	let mut p = Proposal::new(
+		1,
+		10,
+		11,
+		ApprovalPolicy {
+			auto_threshold: 5,
+			approvers: vec!["alice".into(), "bob".into(), "charlie".into()],
+			required: 2,
+			timeout: 600,
+			headroom: 10,
+		},
+	);
+
+	assert!(!p.is_expired());
+	assert!(p.is_approver("bob"));
+	assert!(!p.is_approver("eve"));
+	assert!(!p.approve("alice"));
+	assert!(!p.approve("alice"));
+	assert_eq!(p.progress(), "1/2");
+	assert!(p.approve("bob"));
+	assert_eq!(p.progress(), "2/2");
+}
+#[test]
+fn proposal_rebid_should_keep_approvals() {
+	let mut p = Proposal::new(
+		1,
+		10,
+		11,
+		ApprovalPolicy {
+			auto_threshold: 5,
+			approvers: vec!["alice".into(), "bob".into()],
+			required: 2,
+			timeout: 600,
+			headroom: 10,
+		},
+	);
+
+	assert!(!p.approve("alice"));
+	assert!(p.rebid(1, 11));
+	assert_eq!(p.amount, 11);
+	assert_eq!(p.progress(), "1/2");
+	assert!(!p.rebid(1, 12));
+	assert!(!p.rebid(2, 10));
+	assert_eq!(p.amount, 11);
+	assert!(p.approve("bob"));
+}
+
+fn approval_policy_of(
+	approval: Option<&ApprovalPolicy>,
+	bot: Option<&Bot>,
+	bid: Balance,
+) -> Option<ApprovalPolicy> {
+	match approval {
+		Some(p) if bid > p.auto_threshold => Some(p.clone()),
+		Some(_) => None,
+		None => bot.filter(|b| b.approve_bids).map(Bot::policy),
+	}
+}
+#[test]
+fn approval_policy_of_should_work() {
+	let policy = ApprovalPolicy {
+		auto_threshold: 5,
+		approvers: vec!["alice".into(), "bob".into()],
+		required: 2,
+		timeout: 600,
+		headroom: 10,
+	};
+	let bot = toml::from_str::<Bot>(
+		r#"
+type = "telegram"
+bot-token = "0:x"
+chat-id = "-1"
+approvers = ["1"]
+"#,
+	)
+	.unwrap();
+
+	assert_eq!(approval_policy_of(Some(&policy), Some(&bot), 5), None);
+	assert_eq!(approval_policy_of(Some(&policy), Some(&bot), 6), Some(policy.clone()));
+	assert_eq!(approval_policy_of(None, Some(&bot), 1), Some(bot.policy()));
+	assert_eq!(approval_policy_of(None, Some(&Bot { approve_bids: false, ..bot }), 1), None);
+	assert_eq!(approval_policy_of(None, None, 1), None);
+}
+
+impl Hunter {
+	/// Return the policy if the bid requires the approvals.
+	///
+	/// The approval policy of the bid takes precedence over the bot's, the bids up to its
+	/// threshold go out automatically.
+	pub(super) fn approval_policy_of(&self, bid: Balance) -> Option<ApprovalPolicy> {
+		approval_policy_of(
+			self.configuration.bid.approval.as_ref(),
+			self.configuration.bot.as_ref(),
+			bid,
+		)
+	}
+
+	/// Request the approvals of the bid instead of tendering it, unless it's covered by the pending
+	/// proposal.
+	///
+	/// A bid within the pending proposal's ceiling replaces its amount and keeps the collected
+	/// approvals.
+	pub(super) fn propose(
+		&self,
+		state: &mut State,
+		auction_index: u32,
+		bid: Balance,
+		policy: ApprovalPolicy,
+	) {
+		if let Some(p) = &mut state.proposal {
+			if p.amount == bid && !p.is_expired() {
+				return;
+			}
+			if p.rebid(auction_index, bid) {
+				tracing::info!(
+					"    proposal(#{}) now bids {} with {} approval(s)",
+					p.id,
+					self.token.fmt(bid),
+					p.progress()
+				);
+
+				return;
+			}
+		}
+
+		match &state.proposal {
+			Some(p) if p.is_expired() => {
+				let (id, amount) = (p.id, p.amount);
+				let notification = format!(
+					"proposal(#{id}) of {} has expired with {} approval(s)",
+					self.token.fmt(amount),
+					p.progress()
+				);
+
+				tracing::warn!("    {notification}");
+
+				self.record_decision(
+					state,
+					AuditAction::Expire,
+					auction_index,
+					amount,
+					&notification,
+				);
+				self.notify(
+					&self
+						.context(state, NotificationEvent::TenderSkipped { amount }, notification)
+						.bid(self.token.fmt(amount)),
+				);
+			},
+			// Superseded by a bid above its ceiling, the old ID can't be approved anymore.
+			_ => (),
+		}
+
+		state.proposal = None;
+
+		if state.proposed == Some(bid) {
+			return;
+		}
+
+		let ceiling = policy.ceiling_of(bid, self.configuration.bid.upper_limit);
+		let proposal = Proposal::new(auction_index, bid, ceiling, policy);
+		let id = proposal.id;
+		let action = if self.is_self_funded() {
+			format!("bid {}", self.token.fmt(bid))
+		} else {
+			format!("contribute {}", self.token.fmt(bid - state.bid_amount))
+		};
+		let notification = format!(
+			"    proposal(#{id}): {action} to win, approving bids up to {}, {} approval(s) of [{}] \
+			 are required within {}s",
+			self.token.fmt(ceiling),
+			proposal.policy.required,
+			proposal.policy.approvers.join(", "),
+			proposal.policy.timeout
+		);
+
+		tracing::warn!("{notification}");
+
+		self.record_decision(
+			state,
+			AuditAction::Propose,
+			auction_index,
+			bid,
+			notification.trim_start(),
+		);
+		self.notify(
+			&self
+				.context(
+					state,
+					NotificationEvent::ApprovalRequested { id, amount: bid },
+					notification.trim_start(),
+				)
+				.bid(self.token.fmt(bid)),
+		);
+
+		// Without the bot, the approvals go through the control endpoint.
+		if let Some(b) = &self.configuration.bot {
+			match b.proposal_request(id, notification.trim_start()) {
+				Ok((url, body)) => {
+					let http = self.http.clone();
+
+					tokio::spawn(async move {
+						if let Err(e) = http
+							.post(url)
+							.json(&body)
+							.send()
+							.await
+							.and_then(|r| r.error_for_status())
+						{
+							tracing::warn!("failed to post proposal(#{id}) due to error({e})");
+						}
+					});
+				},
+				Err(e) => tracing::warn!("failed to post proposal(#{id}) due to error({e})"),
+			}
+		}
+
+		state.proposal = Some(proposal);
+		state.proposed = Some(bid);
+	}
+
+	/// Record the approval, tender the proposal once it has enough approvals and is still valid.
+	///
+	/// Return the notification.
+	pub(super) async fn approve(&self, state: &mut State, id: u64, by: &str) -> Result<String> {
+		let Some(p) = state.proposal.as_mut().filter(|p| p.id == id) else {
+			return Ok(format!(
+				"proposal(#{id}) is not pending, approval by {by} has been ignored"
+			));
+		};
+		let (auction_index, amount, ceiling) = (p.auction_index, p.amount, p.ceiling);
+		let a = self.token.fmt(amount);
+
+		let notification = if !p.is_approver(by) {
+			format!("{by} is not an approver of proposal(#{id}), the approval has been ignored")
+		} else if p.is_expired() {
+			state.proposal = None;
+
+			format!("proposal(#{id}) of {a} has expired, the approval by {by} has been ignored")
+		} else if !p.approve(by) {
+			format!(
+				"proposal(#{id}) of {a} has been approved by {by}({}), waiting for more",
+				p.progress()
+			)
+		} else {
+			let progress = p.progress();
+
+			state.proposal = None;
+			// The following bids up to the ceiling go out without asking again.
+			state.approved = Some((auction_index, ceiling));
+
+			let reason = if self.paused || self.watch_only() {
+				Some("tendering has been paused or switched to watch-only")
+			} else if state.auction.as_ref().map(|a| a.index) != Some(auction_index) {
+				Some("its auction has ended")
+			} else if state.is_winner == Some(true) {
+				Some("we are winning already")
+			} else {
+				None
+			};
+
+			if let Some(r) = reason {
+				format!(
+					"proposal(#{id}) of {a} has been approved by {by}({progress}) but ignored since {r}"
+				)
+			} else {
+				let n = format!(
+					"proposal(#{id}) of {a} has been approved by {by}({progress}) and tendered"
+				);
+
+				// Record the decision before the tender's own records.
+				self.record_decision(state, AuditAction::Approve, auction_index, amount, &n);
+				self.tender(state, auction_index, amount).await?;
+
+				return Ok(n);
+			}
+		};
+
+		self.record_decision(state, AuditAction::Approve, auction_index, amount, &notification);
+
+		Ok(notification)
+	}
+
+	/// Drop the rejected proposal, a single rejection is enough.
+	///
+	/// Return the notification.
+	pub(super) fn reject(&self, state: &mut State, id: u64, by: &str) -> String {
+		let Some(p) = state.proposal.as_ref().filter(|p| p.id == id) else {
+			return format!("proposal(#{id}) is not pending, rejection by {by} has been ignored");
+		};
+		let (auction_index, amount) = (p.auction_index, p.amount);
+		let notification = if p.is_approver(by) {
+			state.proposal = None;
+
+			format!("proposal(#{id}) of {} has been rejected by {by}", self.token.fmt(amount))
+		} else {
+			format!("{by} is not an approver of proposal(#{id}), the rejection has been ignored")
+		};
+
+		self.record_decision(state, AuditAction::Reject, auction_index, amount, &notification);
+
+		notification
+	}
+
+	/// Keep the trail of the approvals in the journal and the audit log.
+	fn record_decision(
+		&self,
+		state: &mut State,
+		action: AuditAction,
+		auction_index: u32,
+		amount: Balance,
+		note: &str,
+	) {
+		state.records.push(
+			Record::new(auction_index, state.block_height, state.block_hash, RecordKind::Approval)
+				.amount(amount)
+				.note(note),
+		);
+		self.audit(state, action, amount, note, None, None);
+	}
+}
@@ -0,0 +1,269 @@
+// std
+use std::{
+	fs::{File, OpenOptions},
+	io::{BufRead, BufReader, BufWriter, Write},
+	path::{Path, PathBuf},
+	time::{SystemTime, UNIX_EPOCH},
+};
+// crates.io
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use subxt::config::polkadot::H256;
+// slothunter
+use crate::hunter::*;
+
+/// Previous hash of the first record.
+const GENESIS_HASH: &str = "0x0000000000000000000000000000000000000000000000000000000000000000";
+/// The hash is always the last field, so the record can be verified without re-serializing it.
+const HASH_FIELD: &str = ",\"hash\":\"";
+
+#[derive(Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Audit {
+	pub path: PathBuf,
+}
+
+pub fn sha256(bytes: impl AsRef<[u8]>) -> String {
+	array_bytes::bytes2hex("0x", Sha256::digest(bytes))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AuditAction {
+	Bid,
+	Contribute,
+	/// Tendering was skipped because of the pause, the watch-only mode or the upper limit.
+	Skip,
+	Propose,
+	Approve,
+	Reject,
+	Expire,
+}
+
+/// A decision of the hunter and what it observed.
+#[derive(Debug, Serialize)]
+pub struct AuditEntry {
+	/// UNIX timestamp in seconds.
+	pub timestamp: u64,
+	pub block_height: BlockNumber,
+	pub block_hash: H256,
+	pub auction_index: Option<u32>,
+	pub winning: Vec<Winner>,
+	pub threshold: Balance,
+	pub minimum_bid_to_win: Option<Balance>,
+	pub action: AuditAction,
+	pub amount: Balance,
+	pub note: String,
+	pub extrinsic_hash: Option<H256>,
+	pub dispatch_result: Option<DispatchResult>,
+	/// SHA-256 of the configuration TOML in effect.
+	pub config_hash: String,
+}
+
+#[derive(Serialize)]
+struct Chained<'a> {
+	sequence: u64,
+	#[serde(flatten)]
+	entry: &'a AuditEntry,
+	previous_hash: &'a str,
+}
+
+/// Serialize the entry into a line chained to the previous one, return the line and its hash.
+fn chain(entry: &AuditEntry, sequence: u64, previous_hash: &str) -> Result<(String, String)> {
+	let body = serde_json::to_string(&Chained { sequence, entry, previous_hash })?;
+	let hash = sha256(&body);
+	let line = format!("{}{HASH_FIELD}{hash}\"}}", body.strip_suffix('}').unwrap_or(&body));
+
+	Ok((line, hash))
+}
+
+/// Verify the hash chain, return the number of the records and the hash of the last one.
+pub fn verify(reader: impl BufRead) -> Result<(u64, String)> {
+	let mut previous_hash = GENESIS_HASH.to_owned();
+	let mut sequence = 0;
+
+	for (i, line) in reader.lines().enumerate() {
+		let line = line?;
+		let n = i + 1;
+		let Some((body, hash)) = line.strip_suffix("\"}").and_then(|l| l.rsplit_once(HASH_FIELD))
+		else {
+			anyhow::bail!("line({n}) has no hash");
+		};
+		let body = format!("{body}}}");
+
+		if sha256(&body) != hash {
+			anyhow::bail!("line({n}) has been tampered with, its hash doesn't match");
+		}
+
+		let record = serde_json::from_str::<Value>(&body)?;
+
+		if record["previous_hash"].as_str() != Some(&previous_hash) {
+			anyhow::bail!("line({n}) is not chained to the previous record");
+		}
+		if record["sequence"].as_u64() != Some(sequence) {
+			anyhow::bail!("line({n}) is out of sequence, expected {sequence}");
+		}
+
+		previous_hash = hash.into();
+		sequence += 1;
+	}
+
+	Ok((sequence, previous_hash))
+}
+
+pub fn verify_file(path: &Path) -> Result<(u64, String)> {
+	verify(BufReader::new(File::open(path)?))
+}
+
+/// Append-only audit log, each record is chained to the previous one by its hash.
+#[derive(Debug)]
+pub struct AuditLog {
+	writer: BufWriter<File>,
+	sequence: u64,
+	last_hash: String,
+}
+impl AuditLog {
+	/// Open the log, refuse to append if the existing chain is broken.
+	pub fn open(path: &Path) -> Result<Self> {
+		let (sequence, last_hash) = if path.is_file() {
+			verify_file(path).map_err(|e| {
+				Error::Configuration(format!("audit log({}) is broken, {e}", path.display()))
+			})?
+		} else {
+			(0, GENESIS_HASH.into())
+		};
+		let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+		Ok(Self { writer: BufWriter::new(file), sequence, last_hash })
+	}
+
+	pub fn write(&mut self, entries: &[AuditEntry]) -> Result<()> {
+		for e in entries {
+			let (line, hash) = chain(e, self.sequence, &self.last_hash)?;
+
+			writeln!(self.writer, "{line}")?;
+
+			self.sequence += 1;
+			self.last_hash = hash;
+		}
+
+		self.writer.flush()?;
+
+		Ok(())
+	}
+}
+#[test]
+fn audit_chain_should_work() {
+	let entry = |action, amount| AuditEntry {
+		timestamp: 1,
+		block_height: 2,
+		block_hash: H256::zero(),
+		auction_index: Some(3),
+		winning: vec![Winner { who: [0; 32], para_id: 2000, leases: (0, 7), value: 4 }],
+		threshold: 32,
+		minimum_bid_to_win: Some(5),
+		action,
+		amount,
+		note: "bid with \"KSM(1)\"}".into(),
+		extrinsic_hash: Some(H256::repeat_byte(1)),
+		dispatch_result: Some(Err("Auctions::NotCurrentAuction".into())),
+		config_hash: sha256("[bid]"),
+	};
+	let mut lines = Vec::new();
+	let mut previous_hash = GENESIS_HASH.to_owned();
+
+	for (i, e) in
+		[entry(AuditAction::Propose, 6), entry(AuditAction::Approve, 6), entry(AuditAction::Bid, 6)]
+			.iter()
+			.enumerate()
+	{
+		let (line, hash) = chain(e, i as _, &previous_hash).unwrap();
+
+		lines.push(line);
+		previous_hash = hash;
+	}
+
+	let log = lines.join("\n");
+
+	assert_eq!(verify(log.as_bytes()).unwrap(), (3, previous_hash));
+	assert!(lines[0].contains("\"action\":\"propose\""));
+	assert!(lines[2].contains("\"dispatch_result\":{\"Err\":\"Auctions::NotCurrentAuction\"}"));
+
+	let tampered = log.replacen("\"amount\":6", "\"amount\":7", 1);
+
+	assert_eq!(
+		verify(tampered.as_bytes()).unwrap_err().to_string(),
+		"line(1) has been tampered with, its hash doesn't match"
+	);
+
+	let removed = [lines[0].as_str(), lines[2].as_str()].join("\n");
+
+	assert_eq!(
+		verify(removed.as_bytes()).unwrap_err().to_string(),
+		"line(2) is not chained to the previous record"
+	);
+	assert_eq!(verify(&b""[..]).unwrap(), (0, GENESIS_HASH.into()));
+}
+
+impl Hunter {
+	/// Audit the decision with what has been observed at the current block.
+	///
+	/// The entry is written at once, so it survives a crash or a restart within the block.
+	pub(super) fn audit(
+		&self,
+		state: &State,
+		action: AuditAction,
+		amount: Balance,
+		note: &str,
+		extrinsic_hash: Option<H256>,
+		dispatch_result: Option<DispatchResult>,
+	) {
+		let Some(audit) = &self.audit else { return };
+		let (_, threshold) = state.winning.result();
+		let entry = AuditEntry {
+			timestamp: SystemTime::now()
+				.duration_since(UNIX_EPOCH)
+				.map(|d| d.as_secs())
+				.unwrap_or_default(),
+			block_height: state.block_height,
+			block_hash: state.block_hash,
+			auction_index: state.auction.as_ref().map(|a| a.index),
+			winning: state.winning.0.iter().filter_map(Clone::clone).collect(),
+			threshold,
+			minimum_bid_to_win: self.minimum_bid_to_win(state, threshold),
+			action,
+			amount,
+			note: note.trim_start().into(),
+			extrinsic_hash,
+			dispatch_result,
+			config_hash: self.configuration.hash.clone(),
+		};
+
+		if let Err(e) = audit.lock().unwrap_or_else(|e| e.into_inner()).write(&[entry]) {
+			tracing::error!("failed to write the audit log due to error({e})");
+		}
+	}
+
+	/// Audit the signed extrinsic whose outcome is unknown since submitting or watching it failed.
+	pub(super) fn audit_unwatched(
+		&self,
+		state: &State,
+		action: AuditAction,
+		amount: Balance,
+		extrinsic_hash: H256,
+		result: Result<DispatchResult>,
+	) -> Result<DispatchResult> {
+		result.inspect_err(|e| {
+			self.metrics.tender_failures.inc();
+			self.audit(
+				state,
+				action,
+				amount,
+				&format!("failed to submit or watch the extrinsic due to error({e})"),
+				Some(extrinsic_hash),
+				None,
+			);
+		})
+	}
+}
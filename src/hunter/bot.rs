@@ -0,0 +1,638 @@
+// std
+use std::fmt::Write;
+// crates.io
+use hmac::{Hmac, Mac};
+use reqwest::{Client, Url};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::Sha256;
+use tokio::sync::mpsc::UnboundedSender;
+// slothunter
+use crate::hunter::*;
+
+/// Slack rejects the requests whose timestamps are older than this, so do we.
+const SLACK_SIGNATURE_TTL: u64 = 300;
+
+/// Chat bot which asks for the approval before tendering and answers the queries.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Bot {
+	#[serde(flatten)]
+	pub chat: Chat,
+	/// User IDs which are allowed to approve or reject the proposals.
+	#[serde(default)]
+	pub approvers: Vec<String>,
+	/// Ask for the approval before each tender.
+	#[serde(default = "Bot::default_approve_bids")]
+	pub approve_bids: bool,
+	/// Seconds to wait for the approval.
+	#[serde(default = "Bot::default_timeout")]
+	pub timeout: u64,
+}
+impl Bot {
+	fn default_approve_bids() -> bool {
+		true
+	}
+
+	fn default_timeout() -> u64 {
+		120
+	}
+
+	/// Check the configuration, return the problem if any.
+	pub fn check(&self) -> StdResult<(), String> {
+		match &self.chat {
+			Chat::Telegram { bot_token, chat_id, api_uri } => {
+				if bot_token.is_empty() || chat_id.is_empty() {
+					return Err("`bot-token` and `chat-id` are required".into());
+				}
+				if !util::check_http_uri(api_uri) {
+					return Err(format!("invalid api-uri({api_uri})"));
+				}
+			},
+			Chat::Slack { webhook, signing_secret } => {
+				if !util::check_http_uri(webhook) {
+					return Err(format!("invalid webhook({webhook})"));
+				}
+				if signing_secret.is_empty() {
+					return Err("`signing-secret` is required".into());
+				}
+			},
+		}
+
+		if self.approve_bids && self.approvers.is_empty() {
+			return Err("at least one approver is required to approve the bids".into());
+		}
+		if self.timeout == 0 {
+			return Err("`timeout` must be greater than 0".into());
+		}
+
+		Ok(())
+	}
+
+	/// Policy of the proposals which are only asked by the bot.
+	pub fn policy(&self) -> ApprovalPolicy {
+		ApprovalPolicy {
+			auto_threshold: 0,
+			approvers: self.approvers.clone(),
+			required: 1,
+			timeout: self.timeout,
+			headroom: ApprovalPolicy::default_headroom(),
+		}
+	}
+
+	/// Build the request which posts the proposal with the approve and reject buttons.
+	pub fn proposal_request(&self, id: u64, text: &str) -> Result<(Url, Value)> {
+		Ok(match &self.chat {
+			Chat::Telegram { bot_token, chat_id, api_uri } => (
+				telegram_method(api_uri, bot_token, "sendMessage")?,
+				json!({
+					"chat_id": chat_id,
+					"text": text,
+					"reply_markup": {
+						"inline_keyboard": [[
+							{ "text": "Approve", "callback_data": format!("/approve {id}") },
+							{ "text": "Reject", "callback_data": format!("/reject {id}") },
+						]],
+					},
+				}),
+			),
+			Chat::Slack { webhook, .. } => (
+				webhook.parse()?,
+				json!({
+					"text": text,
+					"blocks": [
+						{ "type": "section", "text": { "type": "mrkdwn", "text": text } },
+						{
+							"type": "actions",
+							"elements": [
+								{
+									"type": "button",
+									"text": { "type": "plain_text", "text": "Approve" },
+									"style": "primary",
+									"action_id": "approve",
+									"value": id.to_string(),
+								},
+								{
+									"type": "button",
+									"text": { "type": "plain_text", "text": "Reject" },
+									"style": "danger",
+									"action_id": "reject",
+									"value": id.to_string(),
+								},
+							],
+						},
+					],
+				}),
+			),
+		})
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Chat {
+	/// Telegram bot, which polls the updates of the chat.
+	#[serde(rename_all = "kebab-case")]
+	Telegram {
+		bot_token: String,
+		chat_id: String,
+		#[serde(default = "default_telegram_api_uri")]
+		api_uri: String,
+	},
+	/// Slack app, which posts through the incoming webhook.
+	///
+	/// The interactivity and the slash command requests are served by the status API.
+	#[serde(rename_all = "kebab-case")]
+	Slack { webhook: String, signing_secret: String },
+}
+fn default_telegram_api_uri() -> String {
+	"https://api.telegram.org".into()
+}
+
+fn telegram_method(api_uri: &str, bot_token: &str, method: &str) -> Result<Url> {
+	Ok(format!("{}/bot{bot_token}/{method}", api_uri.trim_end_matches('/')).parse()?)
+}
+
+#[test]
+fn bot_should_deserialize() {
+	let bot = toml::from_str::<Bot>(
+		r#"
+type = "telegram"
+bot-token = "0:x"
+chat-id = "-1"
+approvers = ["1"]
+"#,
+	)
+	.unwrap();
+
+	assert_eq!(
+		bot,
+		Bot {
+			chat: Chat::Telegram {
+				bot_token: "0:x".into(),
+				chat_id: "-1".into(),
+				api_uri: "https://api.telegram.org".into()
+			},
+			approvers: vec!["1".into()],
+			approve_bids: true,
+			timeout: 120,
+		}
+	);
+	assert_eq!(bot.check(), Ok(()));
+
+	let (url, body) = bot.proposal_request(7, "tender KSM(1) to win").unwrap();
+
+	assert_eq!(url.as_str(), "https://api.telegram.org/bot0:x/sendMessage");
+	assert_eq!(body["reply_markup"]["inline_keyboard"][0][0]["callback_data"], "/approve 7");
+	assert_eq!(body["reply_markup"]["inline_keyboard"][0][1]["callback_data"], "/reject 7");
+	assert!(Bot { approvers: Vec::new(), ..bot }.check().is_err());
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum BotCommand {
+	Status,
+	Approve(u64),
+	Reject(u64),
+}
+impl BotCommand {
+	/// Parse `status`, `approve <id>` or `reject <id>`.
+	///
+	/// The leading slash and the Telegram `@bot` suffix are optional.
+	pub fn parse(s: &str) -> Option<Self> {
+		let mut words = s.split_whitespace();
+		let command = words.next()?.trim_start_matches('/');
+		let command = command.split_once('@').map_or(command, |(c, _)| c);
+		let mut id = || words.next()?.trim_start_matches('#').parse().ok();
+
+		match command {
+			"status" => Some(Self::Status),
+			"approve" => Some(Self::Approve(id()?)),
+			"reject" => Some(Self::Reject(id()?)),
+			_ => None,
+		}
+	}
+}
+#[test]
+fn bot_command_parse_should_work() {
+	assert_eq!(BotCommand::parse("/status"), Some(BotCommand::Status));
+	assert_eq!(BotCommand::parse("/status@slothunter_bot"), Some(BotCommand::Status));
+	assert_eq!(BotCommand::parse("approve #7"), Some(BotCommand::Approve(7)));
+	assert_eq!(BotCommand::parse("/reject 7"), Some(BotCommand::Reject(7)));
+	assert_eq!(BotCommand::parse("/approve"), None);
+	assert_eq!(BotCommand::parse("hello"), None);
+	assert_eq!(BotCommand::parse(""), None);
+}
+
+/// Shared by the chat integrations to handle the commands.
+#[derive(Clone, Debug)]
+pub struct BotContext {
+	pub approvers: Vec<String>,
+	pub status: SharedStatus,
+	pub token: Token,
+	pub names: Names,
+	pub commands: UnboundedSender<Command>,
+}
+impl BotContext {
+	/// Handle the command from the user, return the reply.
+	///
+	/// The decisions will be applied by the hunter before processing the next block.
+	pub fn handle(&self, command: BotCommand, user: &str) -> String {
+		let (command, reply) = match command {
+			BotCommand::Status => {
+				let status = self.status.read().expect("lock must not be poisoned");
+
+				return status_text(&status, &self.token, &self.names);
+			},
+			BotCommand::Approve(_) | BotCommand::Reject(_)
+				if !self.approvers.iter().any(|a| a == user) =>
+				return format!("user({user}) is not an approver"),
+			BotCommand::Approve(id) => (
+				Command::Approve { id, by: user.into() },
+				format!("approval of proposal(#{id}) has been received"),
+			),
+			BotCommand::Reject(id) => (
+				Command::Reject { id, by: user.into() },
+				format!("rejection of proposal(#{id}) has been received"),
+			),
+		};
+
+		tracing::info!("received bot command({command:?})");
+
+		if self.commands.send(command).is_err() {
+			return "hunter is not running".into();
+		}
+
+		reply
+	}
+
+	/// Poll the updates of the Telegram bot and handle the commands of the chat.
+	pub async fn poll_telegram(
+		self,
+		http: Client,
+		bot_token: String,
+		chat_id: String,
+		api_uri: String,
+	) {
+		let mut offset = 0;
+		let mut backoff = util::Backoff::default();
+
+		loop {
+			let updates = match self.telegram_updates(&http, &api_uri, &bot_token, offset).await {
+				Ok(u) => u,
+				Err(e) => {
+					let delay = backoff.next();
+
+					tracing::warn!(
+						"failed to poll the telegram updates due to error({e}), retrying in {delay:?}"
+					);
+					tokio::time::sleep(delay).await;
+
+					continue;
+				},
+			};
+
+			for u in updates {
+				offset = offset.max(u["update_id"].as_i64().unwrap_or_default() + 1);
+
+				let Some(u) = TelegramUpdate::of(&u) else { continue };
+
+				if u.chat_id != chat_id {
+					continue;
+				}
+
+				let Some(command) = BotCommand::parse(&u.text) else { continue };
+				let reply = self.handle(command, &u.user);
+				let mut requests = vec![(
+					"sendMessage",
+					json!({ "chat_id": chat_id, "text": reply, "disable_web_page_preview": true }),
+				)];
+
+				if let Some(id) = u.callback_id {
+					requests.push((
+						"answerCallbackQuery",
+						json!({ "callback_query_id": id, "text": reply }),
+					));
+				}
+
+				for (method, body) in requests {
+					let result = async {
+						http.post(telegram_method(&api_uri, &bot_token, method)?)
+							.json(&body)
+							.send()
+							.await?
+							.error_for_status()?;
+
+						Ok::<_, anyhow::Error>(())
+					};
+
+					if let Err(e) = result.await {
+						tracing::warn!("failed to reply the telegram chat due to error({e})");
+					}
+				}
+			}
+		}
+	}
+
+	async fn telegram_updates(
+		&self,
+		http: &Client,
+		api_uri: &str,
+		bot_token: &str,
+		offset: i64,
+	) -> Result<Vec<Value>> {
+		let response = http
+			.post(telegram_method(api_uri, bot_token, "getUpdates")?)
+			.json(&json!({
+				"offset": offset,
+				"timeout": 30,
+				"allowed_updates": ["message", "callback_query"],
+			}))
+			.send()
+			.await?
+			.error_for_status()?
+			.json::<Value>()
+			.await?;
+
+		Ok(serde_json::from_value(response["result"].clone())?)
+	}
+}
+
+/// A Telegram message or button click.
+#[derive(Debug, PartialEq, Eq)]
+struct TelegramUpdate {
+	chat_id: String,
+	user: String,
+	text: String,
+	/// ID of the button click, which must be answered.
+	callback_id: Option<String>,
+}
+impl TelegramUpdate {
+	fn of(update: &Value) -> Option<Self> {
+		fn id(v: &Value) -> Option<String> {
+			v.as_i64().map(|i| i.to_string()).or_else(|| v.as_str().map(Into::into))
+		}
+
+		if let Some(q) = update.get("callback_query") {
+			Some(Self {
+				chat_id: id(&q["message"]["chat"]["id"])?,
+				user: id(&q["from"]["id"])?,
+				text: q["data"].as_str()?.into(),
+				callback_id: Some(q["id"].as_str()?.into()),
+			})
+		} else {
+			let m = update.get("message")?;
+
+			Some(Self {
+				chat_id: id(&m["chat"]["id"])?,
+				user: id(&m["from"]["id"])?,
+				text: m["text"].as_str()?.into(),
+				callback_id: None,
+			})
+		}
+	}
+}
+#[test]
+fn telegram_update_should_work() {
+	assert_eq!(
+		TelegramUpdate::of(&json!({
+			"update_id": 1,
+			"message": { "from": { "id": 1 }, "chat": { "id": -1 }, "text": "/status" },
+		})),
+		Some(TelegramUpdate {
+			chat_id: "-1".into(),
+			user: "1".into(),
+			text: "/status".into(),
+			callback_id: None
+		})
+	);
+	assert_eq!(
+		TelegramUpdate::of(&json!({
+			"update_id": 2,
+			"callback_query": {
+				"id": "x",
+				"from": { "id": 1 },
+				"message": { "chat": { "id": -1 } },
+				"data": "/approve 7",
+			},
+		})),
+		Some(TelegramUpdate {
+			chat_id: "-1".into(),
+			user: "1".into(),
+			text: "/approve 7".into(),
+			callback_id: Some("x".into())
+		})
+	);
+	assert_eq!(TelegramUpdate::of(&json!({ "update_id": 3, "edited_message": {} })), None);
+}
+
+/// Slack app of the status API.
+#[derive(Clone, Debug)]
+pub struct Slack {
+	pub signing_secret: String,
+	pub bot: BotContext,
+}
+impl Slack {
+	/// Verify the `X-Slack-Signature` of the request.
+	pub fn verify(&self, timestamp: &str, body: &[u8], signature: &str, now: u64) -> bool {
+		let Ok(t) = timestamp.parse::<u64>() else { return false };
+
+		if now.abs_diff(t) > SLACK_SIGNATURE_TTL {
+			return false;
+		}
+
+		let mut mac = Hmac::<Sha256>::new_from_slice(self.signing_secret.as_bytes())
+			.expect("HMAC accepts keys of any size");
+
+		mac.update(format!("v0:{timestamp}:").as_bytes());
+		mac.update(body);
+
+		util::constant_time_eq(
+			&format!("v0={}", array_bytes::bytes2hex("", mac.finalize().into_bytes())),
+			signature,
+		)
+	}
+
+	/// Handle the slash command, such as `/slothunter approve 7`.
+	pub fn command(&self, form: &[u8]) -> Option<String> {
+		let (mut text, mut user) = (None, None);
+
+		for (k, v) in url::form_urlencoded::parse(form) {
+			match &*k {
+				"text" => text = Some(v.into_owned()),
+				"user_id" => user = Some(v.into_owned()),
+				_ => (),
+			}
+		}
+
+		Some(self.bot.handle(BotCommand::parse(&text?)?, &user?))
+	}
+
+	/// Handle the button click of the proposal.
+	pub fn interaction(&self, form: &[u8]) -> Option<String> {
+		let payload = url::form_urlencoded::parse(form).find(|(k, _)| k == "payload")?.1;
+		let payload = serde_json::from_str::<Value>(&payload).ok()?;
+		let action = &payload["actions"][0];
+		let id = action["value"].as_str()?.parse().ok()?;
+		let command = match action["action_id"].as_str()? {
+			"approve" => BotCommand::Approve(id),
+			"reject" => BotCommand::Reject(id),
+			_ => return None,
+		};
+
+		Some(self.bot.handle(command, payload["user"]["id"].as_str()?))
+	}
+}
+#[test]
+fn slack_should_work() {
+	let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+	let slack = Slack {
+		signing_secret: "secret".into(),
+		bot: BotContext {
+			approvers: vec!["U1".into()],
+			status: SharedStatus::default(),
+			token: Token { symbol: "KSM".into(), decimals: 12. },
+			names: Names::default(),
+			commands: sender,
+		},
+	};
+	let body = b"text=approve+7&user_id=U1";
+	let signature = {
+		let mut mac = Hmac::<Sha256>::new_from_slice(b"secret").unwrap();
+
+		mac.update(b"v0:1700000000:text=approve+7&user_id=U1");
+
+		format!("v0={}", array_bytes::bytes2hex("", mac.finalize().into_bytes()))
+	};
+
+	assert!(slack.verify("1700000000", body, &signature, 1700000100));
+	assert!(!slack.verify("1700000000", body, &signature, 1700001000));
+	assert!(!slack.verify("1700000000", b"text=approve+8&user_id=U1", &signature, 1700000100));
+	assert_eq!(slack.command(body).unwrap(), "approval of proposal(#7) has been received");
+	assert!(matches!(
+		receiver.try_recv(),
+		Ok(Command::Approve { id: 7, by }) if by == "U1"
+	));
+	assert_eq!(slack.command(b"text=reject+7&user_id=U2").unwrap(), "user(U2) is not an approver");
+	assert!(receiver.try_recv().is_err());
+
+	let payload = json!({
+		"user": { "id": "U1" },
+		"actions": [{ "action_id": "reject", "value": "7" }],
+	});
+	let form = url::form_urlencoded::Serializer::new(String::new())
+		.append_pair("payload", &payload.to_string())
+		.finish();
+
+	assert_eq!(
+		slack.interaction(form.as_bytes()).unwrap(),
+		"rejection of proposal(#7) has been received"
+	);
+	assert!(matches!(receiver.try_recv(), Ok(Command::Reject { id: 7, .. })));
+}
+
+/// Render the status for the chat.
+pub fn status_text(status: &Status, token: &Token, names: &Names) -> String {
+	let mut s = format!("block(#{}, {:?})", status.block.height, status.block.hash);
+
+	let Some(auction) = &status.state.auction else {
+		s.push_str("\nno active auction");
+
+		return s;
+	};
+	let first = auction.first_lease_period;
+
+	write!(s, "\nauction(#{})", auction.index).expect("writing to a string never fails");
+
+	for w in &status.winners.winners {
+		write!(
+			s,
+			"\n- {} parachain({}) won lease(s)[#{}, #{}] with {}",
+			names.account(&w.who),
+			names.para(w.para_id),
+			first + w.leases.0,
+			first + w.leases.1,
+			token.fmt(w.value)
+		)
+		.expect("writing to a string never fails");
+	}
+
+	write!(s, "\nthreshold {}", token.fmt(status.winners.threshold))
+		.expect("writing to a string never fails");
+
+	if let Some(b) = status.minimum_bid_to_win {
+		write!(s, "\nminimum bid to win {}", token.fmt(b))
+			.expect("writing to a string never fails");
+	}
+	if status.state.bid_amount != 0 {
+		write!(s, "\nour bid {}", token.fmt(status.state.bid_amount))
+			.expect("writing to a string never fails");
+	}
+
+	s
+}
+#[test]
+fn status_text_should_work() {
+	let token = Token { symbol: "KSM".into(), decimals: 12. };
+	let names = Names::new(&Naming {
+		identity: false,
+		paras: [(2000, "Acala".into())].into_iter().collect(),
+	});
+	let status = Status {
+		state: StateStatus {
+			auction: Some(AuctionDetail {
+				index: 1,
+				first_lease_period: 10,
+				ending_period_start_at: 100,
+			}),
+			..Default::default()
+		},
+		winners: WinnersStatus {
+			winners: vec![Winner {
+				who: [0; 32],
+				para_id: 2000,
+				leases: (0, 1),
+				value: 1_000_000_000_000,
+			}],
+			threshold: 1_000_000_000_000,
+		},
+		..Default::default()
+	};
+
+	assert_eq!(
+		status_text(&status, &token, &names).lines().nth(2),
+		Some(
+			"- 5C4hrfjw9DjXZTzV3MwzrrAr9P1MJhSrvWGWqi1eSuyUpnhM parachain(Acala (2000)) won \
+			 lease(s)[#10, #11] with KSM(1)"
+		)
+	);
+}
+
+impl Hunter {
+	pub fn bot_context(&self, bot: &Bot) -> BotContext {
+		let policy = self.configuration.bid.approval.iter().flat_map(|p| &p.approvers);
+
+		BotContext {
+			approvers: bot.approvers.iter().chain(policy).cloned().collect(),
+			status: self.status.clone(),
+			token: self.token.clone(),
+			names: self.names.clone(),
+			commands: self.commands.sender.clone(),
+		}
+	}
+
+	/// Start polling the Telegram bot, the Slack app is served by the status API.
+	pub fn serve_bot(&self) {
+		if let Some(b @ Bot { chat: Chat::Telegram { bot_token, chat_id, api_uri }, .. }) =
+			&self.configuration.bot
+		{
+			tracing::info!("telegram bot is polling chat({chat_id})");
+
+			tokio::spawn(self.bot_context(b).poll_telegram(
+				self.http.clone(),
+				bot_token.clone(),
+				chat_id.clone(),
+				api_uri.clone(),
+			));
+		}
+	}
+}
@@ -1,14 +1,17 @@
 // std
 use std::{
+	collections::{HashMap, HashSet},
 	fmt::{Debug, Display, Formatter, Result as FmtResult},
 	fs,
-	path::PathBuf,
+	net::SocketAddr,
+	path::{Path, PathBuf},
+	time::Duration,
 };
 // crates.io
 use app_dirs2::{AppDataType, AppInfo};
-use serde::Deserialize;
+use serde::{de::Error as DeError, Deserialize, Deserializer};
 use sp_core::{sr25519::Pair, Pair as _};
-use subxt::{backend::rpc::RpcClient, tx::PairSigner};
+use subxt::tx::PairSigner;
 // slothunter
 use crate::hunter::*;
 
@@ -19,71 +22,115 @@ pub const SLOTHUNTER: AppInfo = AppInfo { name: "slothunter", author: "Xavier La
 pub struct ConfigurationToml {
 	pub network: Network,
 	pub graphql_endpoint: Option<String>,
-	pub node_endpoint: Option<String>,
+	#[serde(default)]
+	pub graphql_schema: GraphqlSchema,
+	pub node_endpoint: Option<NodeEndpoint>,
 	pub block_subscription_mode: BlockSubscriptionMode,
 	pub bid: BidToml,
 	pub notification: NotificationToml,
+	pub journal: Option<Journal>,
+	pub audit: Option<Audit>,
+	pub api: Option<Api>,
+	pub bot: Option<Bot>,
+	pub light_client: Option<LightClient>,
+	#[serde(default)]
+	pub supervision: Supervision,
+	#[serde(default)]
+	pub naming: Naming,
+	/// SHA-256 of the TOML, set by [`ConfigurationToml::load`].
+	#[serde(skip)]
+	pub hash: String,
 }
 impl ConfigurationToml {
-	pub fn load(path: Option<PathBuf>) -> Result<Self> {
-		fn initialize(path: &PathBuf) -> Result<ConfigurationToml> {
-			if !path.is_file() {
-				let s = include_str!("../../configuration-template.toml");
-
-				fs::write(path, s)?;
-
-				Ok(toml::from_str(s)?)
-			} else {
-				Ok(toml::from_str(&fs::read_to_string(path)?)?)
-			}
-		}
-
+	/// Locate the configuration TOML's file from the given file or folder.
+	pub fn locate(path: Option<PathBuf>) -> Result<PathBuf> {
 		let path = path.unwrap_or(app_dirs2::app_root(AppDataType::UserConfig, &SLOTHUNTER)?);
 
 		if matches!(path.extension().map(|s| s.to_str().unwrap_or_default()), Some("toml")) {
-			return initialize(&path);
+			return Ok(path);
 		}
 		if !path.is_dir() {
 			fs::create_dir_all(&path)?;
 		}
 
-		let path = path.join("config.toml");
+		Ok(path.join("config.toml"))
+	}
+
+	/// Load the configuration TOML, initialize it with the template if it doesn't exist.
+	pub fn load(path: &Path) -> Result<Self> {
+		if !path.is_file() {
+			let s = include_str!("../../configuration-template.toml");
 
-		initialize(&path)
+			fs::write(path, s)?;
+
+			Ok(Self { hash: sha256(s), ..toml::from_str(s)? })
+		} else {
+			Self::read(path)
+		}
+	}
+
+	/// Like [`Self::load`], but never writes the template over a missing file.
+	pub fn read(path: &Path) -> Result<Self> {
+		let s = fs::read_to_string(path)?;
+
+		Ok(Self { hash: sha256(&s), ..toml::from_str(&s)? })
 	}
 
 	pub fn try_into_configuration(self) -> Result<Configuration> {
+		self.diagnose().into_result()?;
+
 		let Self {
 			network,
 			graphql_endpoint,
+			graphql_schema,
 			node_endpoint,
 			block_subscription_mode,
 			bid:
-				BidToml { para_id, leases, watch_only, r#type, real, delegate, upper_limit, increment },
-			notification: NotificationToml { mail, webhooks },
+				BidToml {
+					para_id,
+					leases,
+					watch_only,
+					r#type,
+					real,
+					delegate,
+					upper_limit,
+					increment,
+					approval,
+				},
+			notification: NotificationToml { mail, webhooks, notifiers, templates },
+			journal,
+			audit,
+			api,
+			bot,
+			light_client,
+			supervision,
+			naming,
+			hash,
 		} = self;
-		let graphql_endpoint =
-			graphql_endpoint.unwrap_or_else(|| network.graphql_endpoint().to_owned());
-		let node_endpoint = node_endpoint.unwrap_or_else(|| network.node_endpoint().to_owned());
+		let node_endpoints = node_endpoint
+			.map(NodeEndpoint::into_vec)
+			.unwrap_or_else(|| network.node_endpoint().into_iter().map(Into::into).collect());
+		let real = util::account_id_of(&real, network.ss58_prefix())?;
 
 		Ok(Configuration {
 			graphql_endpoint,
-			node_endpoint,
+			graphql_schema,
+			node_endpoints,
 			block_subscription_mode,
-			token: network.token(),
+			network,
 			bid: Bid {
 				para_id,
 				leases,
 				watch_only,
 				r#type,
-				real: array_bytes::hex2array(real)
-					.map_err(|e| anyhow::anyhow!("invalid public key, {e:?}"))?,
+				real,
 				delegate: PairSigner::new(Pair::from_seed(
 					&array_bytes::hex2array(delegate)
 						.map_err(|e| anyhow::anyhow!("invalid seed, {e:?}"))?,
 				)),
 				upper_limit: upper_limit.parse()?,
 				increment: increment.parse()?,
+				approval,
 			},
 			notification: Notification {
 				mail: mail
@@ -99,11 +146,33 @@ impl ConfigurationToml {
 								.into_iter()
 								.map(|r| r.parse())
 								.collect::<StdResult<_, _>>()?,
+							// The mail is not for the routine updates by default.
+							subscription: m.subscription.with_default_severity(Severity::Info),
+							throttle: m.throttle,
 						})
 					})
 					.transpose()?,
-				webhooks,
+				notifiers: webhooks
+					.into_iter()
+					.map(|u| Channel {
+						notifier: Notifier::of_webhook(u),
+						subscription: Subscription::default(),
+						policy: DeliveryPolicy::default(),
+						throttle: Throttle::default(),
+						secret: None,
+					})
+					.chain(notifiers)
+					.collect(),
+				templates: Templates::new(&templates)?,
 			},
+			journal,
+			audit,
+			api,
+			bot,
+			light_client,
+			supervision,
+			naming,
+			hash,
 		})
 	}
 }
@@ -118,18 +187,53 @@ pub struct BidToml {
 	pub delegate: String,
 	pub upper_limit: String,
 	pub increment: String,
+	pub approval: Option<ApprovalPolicy>,
 }
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct NotificationToml {
 	pub mail: Option<MailToml>,
+	/// Bare webhook URIs, prefer `notifiers`.
+	#[serde(default)]
 	pub webhooks: Vec<String>,
+	#[serde(default)]
+	pub notifiers: Vec<Channel>,
+	/// Templates of the events, the key is the event name.
+	#[serde(default)]
+	pub templates: HashMap<String, Template>,
 }
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct MailToml {
 	pub sender: SenderToml,
 	pub receivers: Vec<String>,
+	#[serde(flatten)]
+	pub subscription: Subscription,
+	#[serde(flatten)]
+	pub throttle: Throttle,
+}
+#[test]
+fn mail_toml_should_work() {
+	let m = toml::from_str::<MailToml>(
+		r#"
+receivers = ["a@example.com"]
+min-severity = "warning"
+digest = 600
+
+[sender]
+username = "b@example.com"
+password = "x"
+smtp = "smtp.example.com"
+"#,
+	)
+	.unwrap();
+
+	assert_eq!(m.sender.username, "b@example.com");
+	assert_eq!(
+		m.subscription,
+		Subscription { events: Vec::new(), min_severity: Some(Severity::Warning) }
+	);
+	assert_eq!(m.throttle, Throttle { rate_limit: None, digest: Some(600) });
 }
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "kebab-case")]
@@ -141,12 +245,22 @@ pub struct SenderToml {
 
 #[derive(Debug)]
 pub struct Configuration {
-	pub graphql_endpoint: String,
-	pub node_endpoint: String,
+	pub graphql_endpoint: Option<String>,
+	pub graphql_schema: GraphqlSchema,
+	pub node_endpoints: Vec<String>,
 	pub block_subscription_mode: BlockSubscriptionMode,
-	pub token: Token,
+	pub network: Network,
 	pub bid: Bid,
 	pub notification: Notification,
+	pub journal: Option<Journal>,
+	pub audit: Option<Audit>,
+	pub api: Option<Api>,
+	pub bot: Option<Bot>,
+	pub light_client: Option<LightClient>,
+	pub supervision: Supervision,
+	pub naming: Naming,
+	/// SHA-256 of the configuration TOML.
+	pub hash: String,
 }
 pub struct Bid {
 	pub para_id: ParaId,
@@ -157,56 +271,303 @@ pub struct Bid {
 	pub delegate: PairSigner<PolkadotConfig, Pair>,
 	pub upper_limit: Balance,
 	pub increment: Balance,
+	pub approval: Option<ApprovalPolicy>,
 }
 impl Debug for Bid {
 	fn fmt(&self, f: &mut Formatter) -> FmtResult {
 		f.debug_struct("Configuration")
 			.field("para_id", &self.para_id)
-			.field("real", &array_bytes::bytes2hex("0x", self.real))
+			.field("real", &util::ss58(&self.real))
 			.field("type", &self.r#type)
 			.field("delegate", &self.delegate.account_id())
 			.finish()
 	}
 }
-#[derive(Debug)]
+/// Approval policy of the large tenders.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ApprovalPolicy {
+	/// Tenders up to this amount go out without the approval.
+	#[serde(deserialize_with = "util::deserialize_number")]
+	pub auto_threshold: Balance,
+	/// Names of the approvers, or their user IDs if they approve through the bot.
+	pub approvers: Vec<String>,
+	/// Number of the distinct approvals required.
+	#[serde(default = "ApprovalPolicy::default_required")]
+	pub required: usize,
+	/// Seconds to wait for the approvals.
+	#[serde(default = "ApprovalPolicy::default_timeout")]
+	pub timeout: u64,
+	/// Percent above the bid which the approvals cover, so the following higher bids don't need
+	/// new approvals.
+	#[serde(default = "ApprovalPolicy::default_headroom")]
+	pub headroom: u8,
+}
+impl ApprovalPolicy {
+	fn default_required() -> usize {
+		2
+	}
+
+	fn default_timeout() -> u64 {
+		600
+	}
+
+	pub fn default_headroom() -> u8 {
+		10
+	}
+
+	/// Return the amount which the approvals of the bid cover, never above the upper limit.
+	pub fn ceiling_of(&self, bid: Balance, upper_limit: Balance) -> Balance {
+		bid.saturating_add(bid / 100 * self.headroom as Balance).min(upper_limit).max(bid)
+	}
+
+	/// Check the configuration, return the problem if any.
+	pub fn check(&self) -> StdResult<(), String> {
+		let approvers = self.approvers.iter().collect::<HashSet<_>>().len();
+
+		if self.required == 0 {
+			return Err("`required` must be greater than 0".into());
+		}
+		if approvers < self.required {
+			return Err(format!(
+				"{} approval(s) are required but only {approvers} distinct approver(s) are configured",
+				self.required
+			));
+		}
+		if self.timeout == 0 {
+			return Err("`timeout` must be greater than 0".into());
+		}
+
+		Ok(())
+	}
+}
+#[test]
+fn approval_policy_should_work() {
+	let policy = toml::from_str::<ApprovalPolicy>(
+		r#"
+auto-threshold = "5000000000000"
+approvers = ["alice", "bob", "charlie"]
+"#,
+	)
+	.unwrap();
+
+	assert_eq!(
+		policy,
+		ApprovalPolicy {
+			auto_threshold: 5_000_000_000_000,
+			approvers: vec!["alice".into(), "bob".into(), "charlie".into()],
+			required: 2,
+			timeout: 600,
+			headroom: 10,
+		}
+	);
+	assert_eq!(policy.check(), Ok(()));
+	assert_eq!(policy.ceiling_of(100, 1_000), 110);
+	assert_eq!(policy.ceiling_of(100, 105), 105);
+	assert_eq!(policy.ceiling_of(100, 50), 100);
+	assert!(ApprovalPolicy { approvers: vec!["alice".into(), "alice".into()], ..policy.clone() }
+		.check()
+		.is_err());
+	assert!(ApprovalPolicy { required: 0, ..policy }.check().is_err());
+}
+
+#[derive(Debug, PartialEq)]
 pub struct Notification {
 	pub mail: Option<Mail>,
-	pub webhooks: Vec<String>,
+	pub notifiers: Vec<Channel>,
+	pub templates: Templates,
+}
+
+#[derive(Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Journal {
+	pub path: PathBuf,
+	pub format: RecordFormat,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Api {
+	#[serde(default = "Api::default_address")]
+	pub address: SocketAddr,
+	/// Bearer token of the control endpoints.
+	pub token: Option<String>,
+	/// Bearer token of each approver, keyed by the approver's name.
+	///
+	/// The approvals through the control endpoint require these, so the approver can't be forged.
+	#[serde(default)]
+	pub approver_tokens: HashMap<String, String>,
+}
+impl Api {
+	fn default_address() -> SocketAddr {
+		([127, 0, 0, 1], 9615).into()
+	}
+}
+
+#[derive(Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct LightClient {
+	/// Path to the chain spec.
+	pub chain_spec: PathBuf,
+	/// Replace the boot nodes of the chain spec if it's not empty.
+	#[serde(default)]
+	pub bootnodes: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Supervision {
+	/// Number of the failures within the window to escalate.
+	pub max_failures: u32,
+	/// Window in seconds.
+	pub window: u64,
+}
+impl Default for Supervision {
+	fn default() -> Self {
+		Self { max_failures: 5, window: 600 }
+	}
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Naming {
+	/// Display the accounts with their on-chain identities.
+	pub identity: bool,
+	/// Project names of the parachains.
+	#[serde(deserialize_with = "Naming::deserialize_paras")]
+	pub paras: HashMap<ParaId, String>,
+}
+impl Naming {
+	// TOML keys are always strings.
+	fn deserialize_paras<'de, D>(deserializer: D) -> StdResult<HashMap<ParaId, String>, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		<HashMap<String, String>>::deserialize(deserializer)?
+			.into_iter()
+			.map(|(k, v)| {
+				Ok((
+					k.parse()
+						.map_err(|e| D::Error::custom(format!("invalid para id({k}), {e}")))?,
+					v,
+				))
+			})
+			.collect()
+	}
+}
+#[test]
+fn naming_should_work() {
+	let n = toml::from_str::<Naming>(
+		r#"
+identity = true
+
+[paras]
+2000 = "Acala"
+2004 = "Moonbeam"
+"#,
+	)
+	.unwrap();
+
+	assert!(n.identity);
+	assert_eq!(n.paras[&2000], "Acala");
+	assert_eq!(n.paras[&2004], "Moonbeam");
+	assert_eq!(toml::from_str::<Naming>("").unwrap(), Naming::default());
+}
+
+/// A single node endpoint or a list of them.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum NodeEndpoint {
+	One(String),
+	Many(Vec<String>),
+}
+impl NodeEndpoint {
+	pub fn into_vec(self) -> Vec<String> {
+		match self {
+			Self::One(u) => vec![u],
+			Self::Many(u) => u,
+		}
+	}
+}
+#[test]
+fn node_endpoint_should_work() {
+	#[derive(Deserialize)]
+	struct T {
+		e: NodeEndpoint,
+	}
+
+	assert_eq!(toml::from_str::<T>(r#"e = "ws://a""#).unwrap().e.into_vec(), ["ws://a"]);
+	assert_eq!(
+		toml::from_str::<T>(r#"e = ["ws://a", "ws://b"]"#).unwrap().e.into_vec(),
+		["ws://a", "ws://b"]
+	);
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum BlockSubscriptionMode {
 	Best,
 	Finalized,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GraphqlSchema {
+	#[default]
+	Firesquid,
+	GiantSquid,
+	Subquery,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum Network {
 	Polkadot,
 	Kusama,
+	Westend,
+	Rococo,
+	Paseo,
+	/// Any other relaychain, `node-endpoint` is required.
+	Custom,
 }
 impl Network {
-	pub fn graphql_endpoint(&self) -> &'static str {
+	/// Return the default node endpoint, `None` for the custom network.
+	pub fn node_endpoint(&self) -> Option<&'static str> {
 		match self {
-			Self::Polkadot => "https://polkadot.explorer.subsquid.io/graphql",
-			Self::Kusama => "https://kusama.explorer.subsquid.io/graphql",
+			Self::Polkadot => Some("wss://rpc.polkadot.io:443"),
+			Self::Kusama => Some("wss://kusama-rpc.polkadot.io:443"),
+			Self::Westend => Some("wss://westend-rpc.polkadot.io:443"),
+			Self::Rococo => Some("wss://rococo-rpc.polkadot.io:443"),
+			Self::Paseo => Some("wss://paseo.rpc.amforc.com:443"),
+			Self::Custom => None,
 		}
 	}
 
-	pub fn node_endpoint(&self) -> &'static str {
+	/// Return the well-known SS58 prefix, `None` for the custom network.
+	pub fn ss58_prefix(&self) -> Option<u16> {
 		match self {
-			Self::Polkadot => "wss://rpc.polkadot.io:443",
-			Self::Kusama => "wss://kusama-rpc.polkadot.io:443",
+			Self::Polkadot | Self::Paseo => Some(0),
+			Self::Kusama => Some(2),
+			Self::Westend | Self::Rococo => Some(42),
+			Self::Custom => None,
 		}
 	}
 
-	pub fn token(&self) -> Token {
-		match self {
-			Self::Polkadot => Token { symbol: "DOT", decimals: 10. },
-			Self::Kusama => Token { symbol: "KSM", decimals: 12. },
-		}
+	/// Return the well-known token, `None` for the custom network.
+	///
+	/// This is only used before connecting to the chain or as a fallback, the actual one will be
+	/// detected from `system_properties`.
+	pub fn token(&self) -> Option<Token> {
+		let (symbol, decimals) = match self {
+			Self::Polkadot => ("DOT", 10.),
+			Self::Kusama => ("KSM", 12.),
+			Self::Westend => ("WND", 12.),
+			Self::Rococo => ("ROC", 12.),
+			Self::Paseo => ("PAS", 10.),
+			Self::Custom => return None,
+		};
+
+		Some(Token { symbol: symbol.into(), decimals })
 	}
 }
 
@@ -225,30 +586,257 @@ impl Display for BidType {
 	}
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Token {
-	pub symbol: &'static str,
+	pub symbol: String,
 	pub decimals: f64,
 }
 impl Token {
+	/// Read the token from the chain's `system_properties`.
+	///
+	/// Only the first token will be used if the chain has multiple ones.
+	pub fn from_properties(
+		properties: &serde_json::Map<String, serde_json::Value>,
+	) -> Option<Self> {
+		fn first(v: &serde_json::Value) -> &serde_json::Value {
+			v.as_array().and_then(|a| a.first()).unwrap_or(v)
+		}
+
+		let symbol = first(properties.get("tokenSymbol")?).as_str()?;
+		let decimals = first(properties.get("tokenDecimals")?).as_u64()?;
+
+		Some(Self { symbol: symbol.into(), decimals: decimals as _ })
+	}
+
 	pub fn fmt(&self, balance: Balance) -> String {
-		format!("{}({})", self.symbol, balance as f64 / 10_f64.powf(self.decimals))
+		format!("{}({})", self.symbol, self.to_f64(balance))
+	}
+
+	pub fn to_f64(&self, balance: Balance) -> f64 {
+		balance as f64 / 10_f64.powf(self.decimals)
 	}
 }
 
+#[test]
+fn token_from_properties_should_work() {
+	let properties = |s: &str| serde_json::from_str::<serde_json::Map<_, _>>(s).unwrap();
+
+	assert_eq!(
+		Token::from_properties(&properties(
+			r#"{"ss58Format":2,"tokenDecimals":12,"tokenSymbol":"KSM"}"#
+		)),
+		Some(Token { symbol: "KSM".into(), decimals: 12. })
+	);
+	assert_eq!(
+		Token::from_properties(&properties(
+			r#"{"tokenDecimals":[18,18],"tokenSymbol":["UNIT","DOLLAR"]}"#
+		)),
+		Some(Token { symbol: "UNIT".into(), decimals: 18. })
+	);
+	assert_eq!(Token::from_properties(&properties(r#"{"ss58Format":42}"#)), None);
+}
+
 impl Hunter {
 	pub async fn from_configuration(configuration: Configuration) -> Result<Self> {
-		let client = Self::ws_connect(&configuration.node_endpoint).await?;
-		let node = OnlineClient::from_rpc_client(RpcClient::new(client.clone())).await?;
+		let endpoints = Endpoint::connect_all(&configuration).await?;
+		let (token, ss58_prefix) = endpoints[0].chain_properties(&configuration.network).await?;
+
+		let journal = configuration
+			.journal
+			.as_ref()
+			.map(|j| RecordWriter::open(&j.path, j.format))
+			.transpose()?;
+		let audit = configuration
+			.audit
+			.as_ref()
+			.map(|a| AuditLog::open(&a.path).map(Mutex::new))
+			.transpose()?;
+
+		let http = util::http_json_client();
+		let metrics = Arc::<Metrics>::default();
+
+		sp_core::crypto::set_default_ss58_version(ss58_prefix.into());
 
 		Ok(Self {
+			deliveries: DeliveryQueue::spawn(http.clone(), metrics.clone()),
+			throttles: Throttles::default(),
+			incidents: Mutex::default(),
+			names: Names::new(&configuration.naming),
 			configuration,
-			http: util::http_json_client(),
-			_ws_connection: client,
-			node,
+			http,
+			rpc: endpoints[0].rpc.clone(),
+			node: endpoints[0].node.clone(),
+			endpoints,
+			active: 0,
+			token,
 			auction_ending_period: 0,
 			auction_sample_length: 0,
 			bidder: AccountId::default(),
+			journal,
+			audit,
+			status: SharedStatus::default(),
+			metrics,
+			commands: Commands::default(),
+			paused: false,
+			overrides: HashSet::new(),
 		})
 	}
+
+	/// Watch the configuration TOML's file and send the changes to the hunter.
+	pub fn watch_configuration(&self, path: PathBuf) {
+		let sender = self.commands.sender.clone();
+
+		tokio::spawn(async move {
+			let modified_at = |p: &Path| fs::metadata(p).and_then(|m| m.modified()).ok();
+			let mut last_modified_at = modified_at(&path);
+
+			loop {
+				tokio::time::sleep(Duration::from_secs(2)).await;
+
+				let m = modified_at(&path);
+
+				if m == last_modified_at {
+					continue;
+				}
+
+				last_modified_at = m;
+
+				// It might be absent for a moment while an editor is saving it.
+				if m.is_none() {
+					tracing::warn!(
+						"configuration({}) is absent, skip reloading until it comes back",
+						path.display()
+					);
+
+					continue;
+				}
+
+				tracing::info!("configuration({}) has been changed", path.display());
+
+				match ConfigurationToml::read(&path)
+					.and_then(ConfigurationToml::try_into_configuration)
+				{
+					Ok(c) =>
+						if sender.send(Command::Reload(Box::new(c))).is_err() {
+							break;
+						},
+					Err(e) =>
+						tracing::error!("failed to reload the configuration due to error({e})"),
+				}
+			}
+		});
+	}
+
+	/// Return the changed fields which require a restart.
+	fn restart_required_changes(&self, new: &Configuration) -> Vec<&'static str> {
+		let old = &self.configuration;
+
+		[
+			("graphql-endpoint", old.graphql_endpoint == new.graphql_endpoint),
+			("graphql-schema", old.graphql_schema == new.graphql_schema),
+			("node-endpoint", old.node_endpoints == new.node_endpoints),
+			("block-subscription-mode", old.block_subscription_mode == new.block_subscription_mode),
+			("network", old.network == new.network),
+			("bid.para-id", old.bid.para_id == new.bid.para_id),
+			("bid.leases", old.bid.leases == new.bid.leases),
+			("bid.type", old.bid.r#type == new.bid.r#type),
+			("bid.real", old.bid.real == new.bid.real),
+			("bid.delegate", old.bid.delegate.account_id() == new.bid.delegate.account_id()),
+			// The bots capture the approvers.
+			("bid.approval", old.bid.approval == new.bid.approval),
+			("journal", old.journal == new.journal),
+			("audit", old.audit == new.audit),
+			("api", old.api == new.api),
+			("bot", old.bot == new.bot),
+			("light-client", old.light_client == new.light_client),
+			("supervision", old.supervision == new.supervision),
+		]
+		.into_iter()
+		.filter_map(|(f, unchanged)| if unchanged { None } else { Some(f) })
+		.collect()
+	}
+
+	/// Swap in the safe-to-change fields of the new configuration.
+	///
+	/// Only the watch-only, the limits, the notification and the naming are taken, the rest of the
+	/// running configuration is kept as it is. The overrides made through the control API are
+	/// replaced by the values of the file.
+	///
+	/// Return the notification of the result.
+	pub(super) async fn reload(&mut self, new: Configuration, block_hash: &H256) -> String {
+		let changes = self.restart_required_changes(&new);
+
+		if !changes.is_empty() {
+			return format!(
+				"configuration reloading has been rejected, changing field(s)({}) requires a restart",
+				changes.join(", ")
+			);
+		}
+
+		let c = &self.configuration;
+		let changes = [
+			("bid.watch-only", c.bid.watch_only == new.bid.watch_only),
+			("bid.upper-limit", c.bid.upper_limit == new.bid.upper_limit),
+			("bid.increment", c.bid.increment == new.bid.increment),
+			("notification.mail", c.notification.mail == new.notification.mail),
+			("notification.notifiers", c.notification.notifiers == new.notification.notifiers),
+			("notification.templates", c.notification.templates == new.notification.templates),
+			("naming", c.naming == new.naming),
+		]
+		.into_iter()
+		.filter_map(|(f, unchanged)| if unchanged { None } else { Some(f) })
+		.collect::<Vec<_>>();
+		let replaced =
+			changes.iter().filter(|f| self.overrides.contains(*f)).copied().collect::<Vec<_>>();
+		let Configuration {
+			bid: Bid { watch_only, upper_limit, increment, .. },
+			notification,
+			naming,
+			hash,
+			..
+		} = new;
+		let c = &mut self.configuration;
+		let old = (
+			mem::replace(&mut c.bid.watch_only, watch_only),
+			mem::replace(&mut c.bid.upper_limit, upper_limit),
+			mem::replace(&mut c.bid.increment, increment),
+			mem::replace(&mut c.notification, notification),
+			mem::replace(&mut c.naming, naming),
+			mem::replace(&mut c.hash, hash),
+		);
+
+		if let Err(e) = self.check(block_hash).await {
+			let c = &mut self.configuration;
+
+			(
+				c.bid.watch_only,
+				c.bid.upper_limit,
+				c.bid.increment,
+				c.notification,
+				c.naming,
+				c.hash,
+			) = old;
+
+			return format!("configuration reloading has been rejected due to error({e})");
+		}
+
+		self.names.reset(&self.configuration.naming);
+		self.overrides.clear();
+
+		if changes.is_empty() {
+			return "configuration has been reloaded, no field has changed".into();
+		}
+
+		let mut notification =
+			format!("configuration has been reloaded, field(s)({}) changed", changes.join(", "));
+
+		if !replaced.is_empty() {
+			notification.push_str(&format!(
+				", the control API override(s) of field(s)({}) have been replaced",
+				replaced.join(", ")
+			));
+		}
+
+		notification
+	}
 }
@@ -0,0 +1,198 @@
+// crates.io
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+// slothunter
+use crate::hunter::*;
+
+/// Commands which will be applied between blocks.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", tag = "command")]
+pub enum Command {
+	Pause,
+	Resume,
+	WatchOnly {
+		watch_only: bool,
+	},
+	Limits {
+		#[serde(default, deserialize_with = "util::deserialize_option_number")]
+		upper_limit: Option<Balance>,
+		#[serde(default, deserialize_with = "util::deserialize_option_number")]
+		increment: Option<Balance>,
+	},
+	Bid {
+		#[serde(deserialize_with = "util::deserialize_number")]
+		amount: Balance,
+	},
+	/// Approve the bot's proposal.
+	///
+	/// Through the control endpoint, `by` is always the owner of the approver token.
+	Approve {
+		id: u64,
+		#[serde(default)]
+		by: String,
+	},
+	/// Reject the bot's proposal.
+	Reject {
+		id: u64,
+		#[serde(default)]
+		by: String,
+	},
+	/// Reload the configuration, this is not available through the control endpoint.
+	#[serde(skip)]
+	Reload(Box<Configuration>),
+}
+#[test]
+fn command_should_work() {
+	assert!(matches!(
+		serde_json::from_str::<Command>(r#"{"command":"pause"}"#).unwrap(),
+		Command::Pause
+	));
+	assert!(matches!(
+		serde_json::from_str::<Command>(r#"{"command":"watch-only","watch_only":true}"#).unwrap(),
+		Command::WatchOnly { watch_only: true }
+	));
+	assert!(matches!(
+		serde_json::from_str::<Command>(
+			r#"{"command":"limits","upper_limit":"100000000000000000000"}"#
+		)
+		.unwrap(),
+		Command::Limits { upper_limit: Some(100_000_000_000_000_000_000), increment: None }
+	));
+	assert!(matches!(
+		serde_json::from_str::<Command>(r#"{"command":"limits","upper_limit":null,"increment":1}"#)
+			.unwrap(),
+		Command::Limits { upper_limit: None, increment: Some(1) }
+	));
+	assert!(matches!(
+		serde_json::from_str::<Command>(r#"{"command":"bid","amount":10}"#).unwrap(),
+		Command::Bid { amount: 10 }
+	));
+	assert!(matches!(
+		serde_json::from_str::<Command>(r#"{"command":"approve","id":7,"by":"alice"}"#).unwrap(),
+		Command::Approve { id: 7, by } if by == "alice"
+	));
+	assert!(serde_json::from_str::<Command>(r#"{"command":"reload"}"#).is_err());
+}
+
+#[derive(Debug)]
+pub struct Commands {
+	pub sender: UnboundedSender<Command>,
+	receiver: UnboundedReceiver<Command>,
+}
+impl Commands {
+	pub fn try_recv(&mut self) -> Option<Command> {
+		self.receiver.try_recv().ok()
+	}
+}
+impl Default for Commands {
+	fn default() -> Self {
+		let (sender, receiver) = mpsc::unbounded_channel();
+
+		Self { sender, receiver }
+	}
+}
+
+impl Hunter {
+	/// Check the manual bid against our current bid and the upper limit.
+	fn check_manual_bid(&self, state: &State, amount: Balance) -> StdResult<(), String> {
+		if amount <= state.bid_amount {
+			return Err(format!(
+				"it doesn't exceed our current bid {}",
+				self.token.fmt(state.bid_amount)
+			));
+		}
+		if amount > self.configuration.bid.upper_limit {
+			return Err(format!(
+				"it exceeds the upper limit {}",
+				self.token.fmt(self.configuration.bid.upper_limit)
+			));
+		}
+
+		Ok(())
+	}
+
+	/// Apply the pending commands, this should be called between blocks.
+	pub(super) async fn apply_commands(&mut self, state: &mut State) -> Result<()> {
+		while let Some(command) = self.commands.try_recv() {
+			// The configuration can't be serialized, notify with the result only.
+			if let Command::Reload(c) = command {
+				let notification = self.reload(*c, &state.block_hash).await;
+
+				tracing::warn!("{notification}");
+
+				self.notify(&self.context(state, NotificationEvent::Reloaded, notification));
+
+				continue;
+			}
+
+			let notification = match &command {
+				Command::Pause => {
+					self.paused = true;
+
+					"tendering has been paused".into()
+				},
+				Command::Resume => {
+					self.paused = false;
+
+					"tendering has been resumed".into()
+				},
+				Command::WatchOnly { watch_only } => {
+					self.configuration.bid.watch_only = *watch_only;
+					self.overrides.insert("bid.watch-only");
+
+					format!("watch-only has been set to {watch_only}")
+				},
+				Command::Limits { increment, .. }
+					if increment.is_some_and(|i| self.token.to_f64(i) < 1.) =>
+					format!(
+						"limits have been rejected since the increment {} should be at least {}(1)",
+						self.token.fmt(increment.unwrap_or_default()),
+						self.token.symbol
+					),
+				Command::Limits { upper_limit, increment } => {
+					if let Some(u) = upper_limit {
+						self.configuration.bid.upper_limit = *u;
+						self.overrides.insert("bid.upper-limit");
+					}
+					if let Some(i) = increment {
+						self.configuration.bid.increment = *i;
+						self.overrides.insert("bid.increment");
+					}
+
+					format!(
+						"upper limit has been set to {} and increment has been set to {}",
+						self.token.fmt(self.configuration.bid.upper_limit),
+						self.token.fmt(self.configuration.bid.increment)
+					)
+				},
+				Command::Bid { amount } => {
+					let a = self.token.fmt(*amount);
+
+					match (&state.auction, self.check_manual_bid(state, *amount)) {
+						(None, _) => format!(
+							"manual bid with {a} has been ignored since there is no active auction"
+						),
+						(_, Err(e)) => format!("manual bid with {a} has been rejected since {e}"),
+						(Some(auction), Ok(())) => {
+							let index = auction.index;
+
+							// Same gate as the automatic bids: pause, watch-only and approval.
+							self.try_tender(state, index, *amount).await?;
+
+							format!("manual bid with {a} has been submitted to tendering")
+						},
+					}
+				},
+				Command::Approve { id, by } => self.approve(state, *id, by).await?,
+				Command::Reject { id, by } => self.reject(state, *id, by),
+				Command::Reload(_) => unreachable!("reload has been handled above; qed"),
+			};
+
+			tracing::warn!("{notification}");
+
+			self.notify(&self.context(state, NotificationEvent::Control(command), notification));
+		}
+
+		Ok(())
+	}
+}
@@ -0,0 +1,600 @@
+// std
+use std::{
+	fmt::{Display, Formatter, Result as FmtResult, Write},
+	path::Path,
+};
+// crates.io
+use lettre::message::Mailbox;
+use scale_value::ValueDef;
+use serde::Serialize;
+use sp_core::{sr25519::Pair, Pair as _};
+use subxt::{config::polkadot::H256, tx::PairSigner};
+// slothunter
+use crate::hunter::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Level {
+	Ok,
+	/// The hunter can run, but it might not work as expected.
+	Warning,
+	/// The hunter can't run.
+	Problem,
+}
+impl Display for Level {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		match self {
+			Self::Ok => write!(f, "ok"),
+			Self::Warning => write!(f, "warning"),
+			Self::Problem => write!(f, "problem"),
+		}
+	}
+}
+
+#[derive(Debug, Serialize)]
+pub struct Finding {
+	pub item: String,
+	pub level: Level,
+	pub message: String,
+}
+
+/// Result of the configuration checks, all the problems will be collected instead of failing fast.
+#[derive(Debug, Default, Serialize)]
+pub struct Diagnosis {
+	pub findings: Vec<Finding>,
+}
+impl Diagnosis {
+	pub fn push(&mut self, item: impl Into<String>, level: Level, message: impl Into<String>) {
+		self.findings.push(Finding { item: item.into(), level, message: message.into() });
+	}
+
+	/// Push an ok finding if passed, otherwise a problem.
+	pub fn check(&mut self, item: impl Into<String>, passed: bool, message: impl Into<String>) {
+		self.push(item, if passed { Level::Ok } else { Level::Problem }, message);
+	}
+
+	pub fn pass(&mut self, item: impl Into<String>, message: impl Into<String>) {
+		self.push(item, Level::Ok, message);
+	}
+
+	pub fn warn(&mut self, item: impl Into<String>, message: impl Into<String>) {
+		self.push(item, Level::Warning, message);
+	}
+
+	pub fn fail(&mut self, item: impl Into<String>, message: impl Into<String>) {
+		self.push(item, Level::Problem, message);
+	}
+
+	pub fn extend(&mut self, other: Self) {
+		self.findings.extend(other.findings);
+	}
+
+	pub fn warnings(&self) -> impl Iterator<Item = &Finding> {
+		self.findings.iter().filter(|f| f.level == Level::Warning)
+	}
+
+	pub fn problems(&self) -> impl Iterator<Item = &Finding> {
+		self.findings.iter().filter(|f| f.level == Level::Problem)
+	}
+
+	/// Whether the item has any problem.
+	pub fn has_problem(&self, item: &str) -> bool {
+		self.problems().any(|f| f.item == item)
+	}
+
+	pub fn is_ok(&self) -> bool {
+		self.problems().next().is_none()
+	}
+
+	pub fn to_human(&self) -> String {
+		let mut s = String::new();
+
+		for f in &self.findings {
+			let _ = writeln!(s, "[{}] {}: {}", f.level, f.item, f.message);
+		}
+
+		let _ = write!(
+			s,
+			"{} warning(s) and {} problem(s) found",
+			self.warnings().count(),
+			self.problems().count()
+		);
+
+		s
+	}
+
+	pub fn into_result(self) -> Result<()> {
+		if self.is_ok() {
+			Ok(())
+		} else {
+			Err(Error::Configuration(
+				self.problems()
+					.map(|f| format!("{}: {}", f.item, f.message))
+					.collect::<Vec<_>>()
+					.join("; "),
+			))?
+		}
+	}
+}
+#[test]
+fn diagnosis_should_work() {
+	let mut d = Diagnosis::default();
+
+	d.pass("node-endpoint", "ws://127.0.0.1:9944");
+
+	assert!(d.is_ok());
+
+	d.warn("bid.upper-limit", "insufficient balance");
+
+	assert!(d.is_ok());
+
+	d.fail("bid.real", "invalid public key");
+	d.fail("bid.increment", "should be at least KSM(1)");
+
+	assert!(d.has_problem("bid.real"));
+	assert!(!d.has_problem("bid.upper-limit"));
+
+	assert_eq!(
+		d.to_human(),
+		"[ok] node-endpoint: ws://127.0.0.1:9944\n[warning] bid.upper-limit: insufficient balance\n[problem] bid.real: invalid public key\n[problem] bid.increment: should be at least KSM(1)\n1 warning(s) and 2 problem(s) found"
+	);
+	assert_eq!(
+		d.into_result().unwrap_err().to_string(),
+		"invalid configuration, bid.real: invalid public key; bid.increment: should be at least KSM(1)"
+	);
+}
+
+impl ConfigurationToml {
+	/// Check the items which don't require a connection.
+	pub fn diagnose(&self) -> Diagnosis {
+		let mut d = Diagnosis::default();
+		// The token of the custom network is unknown until connected.
+		let token = self.network.token();
+		let fmt = |b: Balance| token.as_ref().map(|t| t.fmt(b)).unwrap_or_else(|| b.to_string());
+
+		let mut check_node_endpoint = |uri: &str| {
+			if uri != LIGHT_CLIENT {
+				d.check("node-endpoint", util::check_ws_uri(uri), format!("uri({uri})"));
+			} else if let Some(l) = &self.light_client {
+				d.check(
+					"light-client.chain-spec",
+					l.chain_spec.is_file(),
+					format!("path({})", l.chain_spec.display()),
+				);
+			} else {
+				d.fail("node-endpoint", "`[light-client]` section is required by the light client");
+			}
+		};
+
+		match &self.node_endpoint {
+			Some(NodeEndpoint::One(uri)) => check_node_endpoint(uri),
+			Some(NodeEndpoint::Many(uris)) if uris.is_empty() =>
+				d.fail("node-endpoint", "at least one endpoint is required"),
+			Some(NodeEndpoint::Many(uris)) => uris.iter().for_each(|uri| check_node_endpoint(uri)),
+			None => match self.network.node_endpoint() {
+				Some(uri) => d.pass("node-endpoint", format!("uri({uri})")),
+				None => d.fail("node-endpoint", "required by the custom network"),
+			},
+		}
+
+		if let Some(uri) = &self.graphql_endpoint {
+			d.check(
+				"graphql-endpoint",
+				util::check_http_uri(uri),
+				format!("uri({uri}, {:?})", self.graphql_schema),
+			);
+		}
+
+		let bid = &self.bid;
+
+		if bid.leases.0 <= bid.leases.1 && bid.leases.1 - bid.leases.0 < C_RANGE_COUNT {
+			d.pass("bid.leases", format!("{:?}", bid.leases));
+		} else {
+			d.fail(
+				"bid.leases",
+				format!(
+					"{:?} should be ordered and span at most {C_RANGE_COUNT} leases",
+					bid.leases
+				),
+			);
+		}
+
+		match util::account_id_of(&bid.real, self.network.ss58_prefix()) {
+			Ok(_) => d.pass("bid.real", &bid.real),
+			Err(e) => d.fail("bid.real", e.to_string()),
+		}
+		match array_bytes::hex2array::<_, 32>(&bid.delegate) {
+			Ok(_) => d.pass("bid.delegate", "valid seed"),
+			Err(e) => d.fail("bid.delegate", format!("invalid seed, {e:?}")),
+		}
+
+		match bid.upper_limit.parse::<Balance>() {
+			Ok(u) => d.pass("bid.upper-limit", fmt(u)),
+			Err(e) =>
+				d.fail("bid.upper-limit", format!("invalid amount({}), {e}", bid.upper_limit)),
+		}
+		match bid.increment.parse::<Balance>() {
+			Ok(i) => match &token {
+				Some(t) if t.to_f64(i) < 1. => d.fail(
+					"bid.increment",
+					format!("{} should be at least {}(1)", t.fmt(i), t.symbol),
+				),
+				_ => d.pass("bid.increment", fmt(i)),
+			},
+			Err(e) => d.fail("bid.increment", format!("invalid amount({}), {e}", bid.increment)),
+		}
+		if let Some(a) = &bid.approval {
+			// Without the bot, only the approvers with their own tokens can approve.
+			let reachable = a
+				.approvers
+				.iter()
+				.filter(|n| self.api.as_ref().is_some_and(|a| a.approver_tokens.contains_key(*n)))
+				.count();
+
+			match a.check() {
+				Ok(_) if self.bot.is_none() && reachable < a.required => d.fail(
+					"bid.approval",
+					format!(
+						"requires the `[bot]` section or at least {} approver(s) in \
+						`api.approver-tokens`, found {reachable}",
+						a.required
+					),
+				),
+				Ok(_) => d.pass(
+					"bid.approval",
+					format!(
+						"{} of {} approver(s) above {}",
+						a.required,
+						a.approvers.len(),
+						fmt(a.auto_threshold)
+					),
+				),
+				Err(e) => d.fail("bid.approval", e),
+			}
+		}
+
+		for uri in &self.notification.webhooks {
+			d.check("notification.webhooks", util::check_http_uri(uri), format!("uri({uri})"));
+		}
+		match Templates::new(&self.notification.templates) {
+			Ok(_) => d.pass(
+				"notification.templates",
+				format!("{} customized event(s)", self.notification.templates.len()),
+			),
+			Err(e) => d.fail("notification.templates", e.to_string()),
+		}
+		for c @ Channel { notifier: n, .. } in &self.notification.notifiers {
+			match c.check() {
+				Ok(_) => d.pass("notification.notifiers", format!("{}({})", n.name(), n.target())),
+				Err(e) => d.fail("notification.notifiers", format!("{}, {e}", n.name())),
+			}
+		}
+
+		if let Some(m) = &self.notification.mail {
+			match m.sender.username.parse::<Mailbox>() {
+				Ok(_) => d.pass("notification.mail.sender.username", &m.sender.username),
+				Err(e) => d.fail(
+					"notification.mail.sender.username",
+					format!("invalid mailbox({}), {e}", m.sender.username),
+				),
+			}
+
+			d.check(
+				"notification.mail.sender.smtp",
+				util::check_smtp_uri(&m.sender.smtp),
+				format!("smtp({})", m.sender.smtp),
+			);
+
+			for r in &m.receivers {
+				match r.parse::<Mailbox>() {
+					Ok(_) => d.pass("notification.mail.receivers", r),
+					Err(e) =>
+						d.fail("notification.mail.receivers", format!("invalid mailbox({r}), {e}")),
+				}
+			}
+			if let Err(e) = m.throttle.check() {
+				d.fail("notification.mail", e);
+			}
+		}
+
+		if let Some(j) = &self.journal {
+			if j.format == RecordFormat::Parquet {
+				d.fail(
+					"journal.format",
+					"parquet can't be appended, use it with `slothunter export`",
+				);
+			}
+		}
+		if let Some(a) = &self.api {
+			let mut tokens = a.token.iter().chain(a.approver_tokens.values()).collect::<Vec<_>>();
+			let count = tokens.len();
+
+			tokens.sort();
+			tokens.dedup();
+
+			// Otherwise, one token could act as several approvers.
+			if tokens.len() != count {
+				d.fail("api", "`token` and `approver-tokens` must be distinct");
+			}
+		}
+		if let Some(b) = &self.bot {
+			match b.check() {
+				Ok(_) if matches!(b.chat, Chat::Slack { .. }) && self.api.is_none() =>
+					d.fail("bot", "slack requires the `[api]` section to serve its requests"),
+				Ok(_) => d.pass("bot", format!("{} approver(s)", b.approvers.len())),
+				Err(e) => d.fail("bot", e),
+			}
+		}
+
+		d
+	}
+
+	/// Run all the checks of the configuration file.
+	///
+	/// A missing file is reported instead of being initialized with the template.
+	pub async fn diagnose_file(path: &Path) -> Diagnosis {
+		if !path.is_file() {
+			let mut d = Diagnosis::default();
+
+			d.fail("configuration", format!("file({}) not found", path.display()));
+
+			return d;
+		}
+
+		match Self::read(path) {
+			Ok(c) => c.diagnose_all().await,
+			Err(e) => {
+				let mut d = Diagnosis::default();
+
+				d.fail("configuration", format!("failed to read file({}), {e}", path.display()));
+
+				d
+			},
+		}
+	}
+
+	/// Run all the checks, including the ones against the chain state at the latest block.
+	///
+	/// The chain checks still run if the configuration is invalid, only the ones which depend on
+	/// the invalid items are skipped.
+	pub async fn diagnose_all(self) -> Diagnosis {
+		let mut d = self.diagnose();
+
+		if d.has_problem("node-endpoint") || d.has_problem("light-client.chain-spec") {
+			d.warn("chain", "skipped since the node endpoint is invalid");
+
+			return d;
+		}
+
+		let real = self.bid.real.clone();
+		let r = async {
+			let hunter = Hunter::from_configuration(self.into_chain_configuration()?).await?;
+			let block_hash = hunter.node.blocks().at_latest().await?.hash();
+			let mut chain = hunter.diagnose(&block_hash, &d).await?;
+
+			// The SS58 prefix of the custom network is unknown until connected.
+			if !d.has_problem("bid.real") {
+				let prefix = sp_core::crypto::default_ss58_version().into();
+
+				if let Err(e) = util::account_id_of(&real, Some(prefix)) {
+					chain.fail("bid.real", e.to_string());
+				}
+			}
+
+			Ok::<_, anyhow::Error>(chain)
+		};
+
+		match r.await {
+			Ok(chain) => d.extend(chain),
+			Err(e) => d.fail("chain", format!("failed to check due to error({e})")),
+		}
+
+		d
+	}
+
+	/// Build the configuration of the chain checks, which tolerates the invalid items.
+	///
+	/// The invalid items are replaced with placeholders, whose checks are skipped by
+	/// [`Hunter::diagnose`]. The sections which the chain checks don't use are dropped, so nothing
+	/// will be opened or written.
+	fn into_chain_configuration(self) -> Result<Configuration> {
+		let Self {
+			network,
+			graphql_schema,
+			node_endpoint,
+			block_subscription_mode,
+			bid,
+			light_client,
+			supervision,
+			naming,
+			hash,
+			..
+		} = self;
+		let node_endpoints = node_endpoint
+			.map(NodeEndpoint::into_vec)
+			.unwrap_or_else(|| network.node_endpoint().into_iter().map(Into::into).collect());
+
+		Ok(Configuration {
+			graphql_endpoint: None,
+			graphql_schema,
+			node_endpoints,
+			block_subscription_mode,
+			network,
+			bid: Bid {
+				para_id: bid.para_id,
+				leases: bid.leases,
+				watch_only: bid.watch_only,
+				r#type: bid.r#type,
+				real: util::account_id_of(&bid.real, None).unwrap_or_default(),
+				delegate: PairSigner::new(Pair::from_seed(
+					&array_bytes::hex2array(&bid.delegate).unwrap_or_default(),
+				)),
+				upper_limit: bid.upper_limit.parse().unwrap_or_default(),
+				increment: bid.increment.parse().unwrap_or_default(),
+				approval: None,
+			},
+			notification: Notification {
+				mail: None,
+				notifiers: Vec::new(),
+				templates: Templates::new(&Default::default())?,
+			},
+			journal: None,
+			audit: None,
+			api: None,
+			bot: None,
+			light_client,
+			supervision,
+			naming,
+			hash,
+		})
+	}
+}
+
+impl Hunter {
+	/// Check the items against the chain state at the given block.
+	///
+	/// The checks which depend on the problems of the `known` diagnosis are skipped.
+	pub async fn diagnose(&self, block_hash: &H256, known: &Diagnosis) -> Result<Diagnosis> {
+		let mut d = Diagnosis::default();
+		let bid = &self.configuration.bid;
+		let token = &self.token;
+		let skip = |d: &mut Diagnosis, item: &str, dependencies: &[&str]| {
+			let skipped = dependencies.iter().any(|i| known.has_problem(i));
+
+			if skipped {
+				d.warn(item, format!("skipped since {} is invalid", dependencies.join(" or ")));
+			}
+
+			skipped
+		};
+
+		// Only the custom network's token is unknown in the static checks.
+		if self.configuration.network.token().is_none()
+			&& !skip(&mut d, "bid.increment", &["bid.increment"])
+			&& token.to_f64(bid.increment) < 1.
+		{
+			d.fail(
+				"bid.increment",
+				format!("{} should be at least {}(1)", token.fmt(bid.increment), token.symbol),
+			);
+		}
+
+		if self.para_registered_at(block_hash, bid.para_id).await? {
+			d.pass("bid.para-id", format!("parachain({}) is registered", bid.para_id));
+		} else {
+			d.fail(
+				"bid.para-id",
+				format!("parachain({}) is not registered in `Registrar::Paras`", bid.para_id),
+			);
+		}
+
+		if skip(&mut d, "bid.leases", &["bid.leases"]) {
+			// The lease checks depend on a valid range.
+		} else if let Some(a) = self.auction_at(block_hash).await? {
+			let range = util::range_of(a.first_lease_period);
+
+			if util::check_leases(&range, &bid.leases) {
+				d.pass(
+					"bid.leases",
+					format!("{:?} within the current auction's range{range:?}", bid.leases),
+				);
+			} else {
+				d.warn(
+					"bid.leases",
+					format!("{:?} out of the current auction's range{range:?}", bid.leases),
+				);
+			}
+		} else {
+			let height = self.node.blocks().at(block_hash.to_owned()).await?.number();
+			let current = self.lease_period_index_at(height)?;
+
+			d.check(
+				"bid.leases",
+				bid.leases.0 >= current,
+				format!(
+					"{:?} not before the current lease period({current}), no auction is active",
+					bid.leases
+				),
+			);
+		}
+
+		if !self.is_self_funded() {
+			match self.fund_index_at(block_hash).await? {
+				Some(i) => d.pass("bid.type", format!("crowdloan fund index({i})")),
+				None => d.fail(
+					"bid.type",
+					format!("no existing crowdloan found for parachain({})", bid.para_id),
+				),
+			}
+		}
+
+		if self.watch_only() {
+			d.pass("bid.delegate", "skipped in watch-only mode");
+
+			return Ok(d);
+		}
+
+		let real = &bid.real;
+
+		if self.is_self_funded()
+			&& !skip(&mut d, "bid.upper-limit", &["bid.real", "bid.upper-limit"])
+		{
+			let free = self.free_balance_at(block_hash, real).await?;
+
+			d.push(
+				"bid.upper-limit",
+				if free >= bid.upper_limit { Level::Ok } else { Level::Warning },
+				format!(
+					"real account's free balance {} versus upper limit {}",
+					token.fmt(free),
+					token.fmt(bid.upper_limit)
+				),
+			);
+		}
+		if skip(&mut d, "bid.delegate", &["bid.real", "bid.delegate"]) {
+			return Ok(d);
+		}
+
+		let delegate = bid.delegate.account_id();
+
+		match self
+			.proxies_at(block_hash, real)
+			.await?
+			.unwrap_or_default()
+			.into_iter()
+			.find(|p| p.delegate.r#type == delegate.0)
+		{
+			Some(p) => {
+				d.pass(
+					"bid.delegate",
+					format!(
+						"delegate({}) is a proxy of real({})",
+						util::ss58(&delegate.0),
+						util::ss58(real)
+					),
+				);
+
+				match p.proxy_type.value {
+					ValueDef::Variant(v) if matches!(v.name.as_str(), "All" | "Auction") =>
+						d.pass("bid.delegate", format!("proxy type({})", v.name)),
+					v => d.fail(
+						"bid.delegate",
+						format!(
+							"proxy type({v:?}) should be `ProxyType::All` or `ProxyType::Auction`"
+						),
+					),
+				}
+			},
+			None => d.fail(
+				"bid.delegate",
+				format!(
+					"delegate({}) is not a proxy of real({})",
+					util::ss58(&delegate.0),
+					util::ss58(real)
+				),
+			),
+		}
+
+		Ok(d)
+	}
+}
@@ -0,0 +1,411 @@
+// std
+use std::pin::Pin;
+// crates.io
+use futures::{
+	future,
+	stream::{self, SelectAll},
+	Stream, StreamExt,
+};
+use subxt::backend::{legacy::LegacyRpcMethods, StreamOfResults};
+// slothunter
+use crate::hunter::*;
+
+/// A connected node endpoint.
+#[derive(Debug)]
+pub struct Endpoint {
+	pub uri: String,
+	/// Websocket connection, this is `None` for the light client.
+	ws: Option<Arc<WsClient>>,
+	pub(super) rpc: RpcClient,
+	pub(super) node: OnlineClient<PolkadotConfig>,
+}
+impl Endpoint {
+	async fn connect(uri: &str, light_client: Option<&LightClient>) -> Result<Self> {
+		let (ws, rpc) = if uri == LIGHT_CLIENT {
+			let Some(l) = light_client else {
+				Err(Error::Configuration("`[light-client]` section is required".into()))?
+			};
+
+			(None, RpcClient::new(LightClientRpc::new(l)?))
+		} else {
+			let (tx, rx) = WsTransportClientBuilder::default().build(uri.parse()?).await?;
+			let ws = Arc::new(WsClientBuilder::default().build_with_tokio(tx, rx));
+
+			(Some(ws.clone()), RpcClient::new(ws))
+		};
+		let node = OnlineClient::from_rpc_client(rpc.clone()).await?;
+
+		Ok(Self { uri: uri.into(), ws, rpc, node })
+	}
+
+	/// Connect to the configured endpoints, the unavailable ones will be skipped.
+	pub async fn connect_all(configuration: &Configuration) -> Result<Vec<Self>> {
+		let mut endpoints = Vec::new();
+
+		for uri in &configuration.node_endpoints {
+			match Self::connect(uri, configuration.light_client.as_ref()).await {
+				Ok(e) => endpoints.push(e),
+				Err(e) =>
+					tracing::warn!("failed to connect to node endpoint({uri}) due to error({e})"),
+			}
+		}
+
+		if endpoints.is_empty() {
+			Err(Error::Transient("no node endpoint is available".into()))?;
+		}
+
+		Ok(endpoints)
+	}
+
+	/// Detect the token from `system_properties` and the SS58 prefix from the metadata.
+	///
+	/// Fall back to the well-known token of the network if the chain doesn't provide one.
+	pub async fn chain_properties(&self, network: &Network) -> Result<(Token, u16)> {
+		let properties =
+			LegacyRpcMethods::<PolkadotConfig>::new(self.rpc.clone()).system_properties().await?;
+		let token =
+			Token::from_properties(&properties).or_else(|| network.token()).ok_or_else(|| {
+				Error::Configuration(format!(
+					"node endpoint({}) doesn't provide the token in `system_properties`",
+					self.uri
+				))
+			})?;
+		let ss58_prefix = self
+			.node
+			.constants()
+			.at(&subxt::dynamic::constant("System", "SS58Prefix"))?
+			.to_value()?
+			.as_u128()
+			.ok_or_else(|| Error::decode("`System::SS58Prefix`"))? as _;
+
+		Ok((token, ss58_prefix))
+	}
+
+	fn is_connected(&self) -> bool {
+		// The light client manages its own connections.
+		self.ws.as_ref().map(|w| w.is_connected()).unwrap_or(true)
+	}
+}
+
+type Item = (usize, StdResult<Block, subxt::Error>);
+
+/// Failed resubscriptions in a row, after which the endpoint is reported as down.
+const MAX_RESUBSCRIPTIONS: u32 = 3;
+
+async fn subscribe(
+	node: &OnlineClient<PolkadotConfig>,
+	mode: &BlockSubscriptionMode,
+) -> StdResult<StreamOfResults<Block>, subxt::Error> {
+	match mode {
+		BlockSubscriptionMode::Best => node.blocks().subscribe_best().await,
+		BlockSubscriptionMode::Finalized => node.blocks().subscribe_finalized().await,
+	}
+}
+
+/// Yield the blocks of the endpoint, resubscribe once the subscription fails or closes.
+///
+/// The resubscriptions never give up, they back off instead. After [`MAX_RESUBSCRIPTIONS`] failed
+/// ones in a row, the endpoint is reported as down through the `endpoint_down` metric until it
+/// recovers.
+fn resubscribing(
+	i: usize,
+	uri: String,
+	node: OnlineClient<PolkadotConfig>,
+	mode: BlockSubscriptionMode,
+	subscription: Option<StreamOfResults<Block>>,
+	metrics: Arc<Metrics>,
+) -> Pin<Box<dyn Stream<Item = Item> + Send>> {
+	Box::pin(stream::unfold(
+		(subscription, util::Backoff::default(), 0),
+		move |(subscription, mut backoff, mut failures)| {
+			let (uri, node, mode, metrics) =
+				(uri.clone(), node.clone(), mode.clone(), metrics.clone());
+
+			async move {
+				let mut subscription = subscription;
+
+				loop {
+					let mut s = match subscription.take() {
+						Some(s) => s,
+						None => match subscribe(&node, &mode).await {
+							Ok(s) => {
+								if failures >= MAX_RESUBSCRIPTIONS {
+									tracing::info!(
+										"node endpoint({uri}) has recovered after {failures} failed resubscription(s)"
+									);
+								}
+
+								failures = 0;
+								metrics.endpoint_down.with_label_values(&[&uri]).set(0);
+
+								s
+							},
+							Err(e) => {
+								let delay = backoff.next();
+
+								failures += 1;
+
+								if failures == MAX_RESUBSCRIPTIONS {
+									tracing::error!(
+										"node endpoint({uri}) is down after {failures} failed resubscription(s), keep retrying"
+									);
+
+									metrics.endpoint_down.with_label_values(&[&uri]).set(1);
+								}
+
+								tracing::warn!(
+									"failed to resubscribe to the blocks on node endpoint({uri}) due to error({e}), retrying in {delay:?}"
+								);
+
+								tokio::time::sleep(delay).await;
+
+								// Let the merged stream tell whether all the endpoints are down.
+								return Some(((i, Err(e)), (None, backoff, failures)));
+							},
+						},
+					};
+
+					match s.next().await {
+						Some(Ok(b)) => return Some(((i, Ok(b)), (Some(s), backoff, failures))),
+						Some(Err(e)) => tracing::warn!(
+							"failed to get the next block from node endpoint({uri}) due to error({e}), resubscribing"
+						),
+						None => tracing::warn!(
+							"block subscription on node endpoint({uri}) has been closed, resubscribing"
+						),
+					}
+				}
+			}
+		},
+	))
+}
+
+/// Merged block subscriptions of all the endpoints.
+///
+/// A block will be yielded by the endpoint which sees it first, the duplicates and the blocks
+/// below the highest one will be dropped.
+pub struct BlockStream {
+	stream: SelectAll<Pin<Box<dyn Stream<Item = Item> + Send>>>,
+	/// The highest block number and the hashes seen at it, more than one on a reorg.
+	highest: Option<(BlockNumber, Vec<H256>)>,
+	/// Failed resubscriptions in a row of each endpoint.
+	failures: Vec<u32>,
+}
+impl BlockStream {
+	/// Record the block, return `false` if it's a duplicate or older than the highest one.
+	fn is_new(&mut self, number: BlockNumber, hash: H256) -> bool {
+		match &mut self.highest {
+			Some((n, _)) if number < *n => false,
+			Some((n, hashes)) if number == *n =>
+				if hashes.contains(&hash) {
+					false
+				} else {
+					hashes.push(hash);
+
+					true
+				},
+			_ => {
+				self.highest = Some((number, vec![hash]));
+
+				true
+			},
+		}
+	}
+
+	/// Return the next block and the index of the endpoint which sees it first.
+	///
+	/// Fail once all the endpoints are down, which leaves the reconnection to the supervisor.
+	pub async fn next(&mut self) -> Result<(usize, Block)> {
+		loop {
+			let Some((i, b)) = self.stream.next().await else {
+				Err(Error::Transient("all the block subscriptions have been closed".into()))?
+			};
+			// The failures have been logged and resubscribed by the endpoint's stream.
+			let b = match b {
+				Ok(b) => {
+					self.failures[i] = 0;
+
+					b
+				},
+				Err(_) => {
+					self.failures[i] += 1;
+
+					if self.is_down() {
+						Err(Error::Transient("all the node endpoints are down".into()))?;
+					}
+
+					continue;
+				},
+			};
+
+			if !self.is_new(b.number(), b.hash()) {
+				continue;
+			}
+
+			return Ok((i, b));
+		}
+	}
+
+	fn is_down(&self) -> bool {
+		self.failures.iter().all(|f| *f >= MAX_RESUBSCRIPTIONS)
+	}
+}
+#[test]
+fn block_stream_is_new_should_work() {
+	let mut s =
+		BlockStream { stream: stream::select_all(Vec::new()), highest: None, failures: Vec::new() };
+
+	assert!(s.is_new(10, H256::repeat_byte(1)));
+	assert!(!s.is_new(10, H256::repeat_byte(1)));
+	// Reorg.
+	assert!(s.is_new(10, H256::repeat_byte(2)));
+	assert!(s.is_new(11, H256::repeat_byte(3)));
+	// Replayed by a lagging endpoint.
+	assert!(!s.is_new(9, H256::repeat_byte(4)));
+	assert!(!s.is_new(10, H256::repeat_byte(5)));
+}
+#[test]
+fn block_stream_is_down_should_work() {
+	let mut s =
+		BlockStream { stream: stream::select_all(Vec::new()), highest: None, failures: vec![0; 2] };
+
+	assert!(!s.is_down());
+
+	s.failures[0] = MAX_RESUBSCRIPTIONS;
+
+	assert!(!s.is_down());
+
+	s.failures[1] = MAX_RESUBSCRIPTIONS + 1;
+
+	assert!(s.is_down());
+}
+
+impl Hunter {
+	/// Subscribe to the blocks on all the endpoints.
+	pub(super) async fn subscribe_blocks(&self) -> Result<BlockStream> {
+		let mut streams = Vec::new();
+		let mut subscribed = false;
+
+		for (i, e) in self.endpoints.iter().enumerate() {
+			let mode = &self.configuration.block_subscription_mode;
+			// Keep the failed ones, they resubscribe in the background.
+			let subscription = subscribe(&e.node, mode)
+				.await
+				.map_err(|err| {
+					tracing::warn!(
+						"failed to subscribe to the blocks on node endpoint({}) due to error({err})",
+						e.uri
+					)
+				})
+				.ok();
+
+			if subscription.is_some() {
+				subscribed = true;
+
+				self.metrics.endpoint_down.with_label_values(&[&e.uri]).set(0);
+			}
+
+			streams.push(resubscribing(
+				i,
+				e.uri.clone(),
+				e.node.clone(),
+				mode.clone(),
+				subscription,
+				self.metrics.clone(),
+			));
+		}
+
+		if !subscribed {
+			Err(Error::Transient("failed to subscribe to the blocks on any endpoint".into()))?;
+		}
+
+		Ok(BlockStream {
+			failures: vec![0; streams.len()],
+			stream: stream::select_all(streams),
+			highest: None,
+		})
+	}
+
+	/// Use the endpoint for the following queries.
+	pub(super) fn activate(&mut self, index: usize) {
+		let e = &self.endpoints[index];
+
+		if self.active != index {
+			tracing::info!("switched to node endpoint({})", e.uri);
+		}
+
+		self.node = e.node.clone();
+		self.rpc = e.rpc.clone();
+		self.active = index;
+	}
+
+	/// Submit the encoded extrinsic to all the endpoints except the active one.
+	///
+	/// This is only for the faster propagation, the failures will be ignored.
+	pub(super) async fn broadcast(&self, extrinsic: &[u8]) {
+		// At once, so a slow endpoint doesn't hold the others.
+		future::join_all(self.endpoints.iter().enumerate().filter(|(i, _)| *i != self.active).map(
+			|(_, e)| async move {
+				let rpc = LegacyRpcMethods::<PolkadotConfig>::new(e.rpc.clone());
+
+				if let Err(err) = rpc.author_submit_extrinsic(extrinsic).await {
+					tracing::debug!(
+						"failed to broadcast the extrinsic to node endpoint({}) due to error({err})",
+						e.uri
+					);
+				}
+			},
+		))
+		.await;
+	}
+
+	/// Return `true` if the active endpoint is connected, the backups don't matter.
+	pub fn ws_is_connected(&self) -> bool {
+		self.endpoints.get(self.active).is_some_and(Endpoint::is_connected)
+	}
+
+	/// Return `true` if all the configured endpoints are connected.
+	pub fn ws_all_connected(&self) -> bool {
+		self.endpoints.len() == self.configuration.node_endpoints.len()
+			&& self.endpoints.iter().all(Endpoint::is_connected)
+	}
+
+	/// Drop the disconnected endpoints and try to connect to the missing ones.
+	///
+	/// Succeed if any endpoint is available.
+	pub async fn ws_reconnect(&mut self, tried: &mut bool) -> Result<()> {
+		if *tried {
+			tokio::time::sleep(Duration::from_secs(5)).await;
+		}
+
+		*tried = true;
+
+		self.endpoints.retain(Endpoint::is_connected);
+
+		for uri in &self.configuration.node_endpoints {
+			if self.endpoints.iter().any(|e| &e.uri == uri) {
+				continue;
+			}
+
+			match Endpoint::connect(uri, self.configuration.light_client.as_ref()).await {
+				Ok(e) => self.endpoints.push(e),
+				Err(e) =>
+					tracing::warn!("failed to connect to node endpoint({uri}) due to error({e})"),
+			}
+		}
+
+		if self.endpoints.is_empty() {
+			Err(Error::Transient("no node endpoint is available".into()))?;
+		}
+
+		// Keep the configured order, the first one is preferred.
+		self.endpoints
+			.sort_by_key(|e| self.configuration.node_endpoints.iter().position(|u| u == &e.uri));
+		// Force to switch, since the indices might have been changed.
+		self.active = usize::MAX;
+		self.activate(0);
+		self.metrics.ws_reconnects.inc();
+
+		Ok(())
+	}
+}
@@ -0,0 +1,54 @@
+// crates.io
+use thiserror::Error as ThisError;
+
+#[derive(Debug, ThisError)]
+pub enum Error {
+	/// The configuration is invalid, the hunter must stop.
+	#[error("invalid configuration, {0}")]
+	Configuration(String),
+	/// The request failed, it might succeed after a retry.
+	#[error("transient error, {0}")]
+	Transient(String),
+	/// The data can't be decoded, the types might need an update after a runtime upgrade.
+	#[error("failed to decode {0}, the types might need an update after a runtime upgrade")]
+	Decode(String),
+}
+impl Error {
+	pub fn decode(what: impl Into<String>) -> Self {
+		Self::Decode(what.into())
+	}
+}
+impl From<anyhow::Error> for Error {
+	fn from(e: anyhow::Error) -> Self {
+		let e = match e.downcast::<Self>() {
+			Ok(e) => return e,
+			Err(e) => e,
+		};
+
+		match e.downcast_ref::<subxt::Error>() {
+			Some(
+				subxt::Error::Codec(_)
+				| subxt::Error::Decode(_)
+				| subxt::Error::Metadata(_)
+				| subxt::Error::MetadataDecoding(_),
+			) => Self::Decode(e.to_string()),
+			_ => Self::Transient(e.to_string()),
+		}
+	}
+}
+#[test]
+fn error_from_anyhow_should_work() {
+	assert!(matches!(
+		Error::from(anyhow::Error::from(Error::Configuration("leases".into()))),
+		Error::Configuration(_)
+	));
+	assert!(matches!(
+		Error::from(anyhow::Error::from(subxt::Error::Other("connection closed".into()))),
+		Error::Transient(_)
+	));
+	assert!(matches!(
+		Error::from(anyhow::Error::from(subxt::Error::Codec("invalid".into()))),
+		Error::Decode(_)
+	));
+	assert!(matches!(Error::from(anyhow::anyhow!("unknown")), Error::Transient(_)));
+}
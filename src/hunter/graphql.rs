@@ -1,122 +1,489 @@
-// std
-use std::fmt::{Display, Formatter, Result as FmtResult};
 // crates.io
-use serde::de::DeserializeOwned;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
 // slothunter
 use crate::hunter::*;
 
+// https://github.com/subsquid/squid-sdk/tree/master/substrate/substrate-explorer
+const SQUID_EVENTS: &str = "\
+query Events($limit: Int!, $offset: Int!, $orderBy: [EventOrderByInput!], $where: EventWhereInput) {
+	events(limit: $limit, offset: $offset, orderBy: $orderBy, where: $where) {
+		args
+		block { height }
+	}
+}";
+// https://academy.subquery.network/run_publish/query.html
+const SUBQUERY_EVENTS: &str = "\
+query Events($first: Int!, $offset: Int!, $orderBy: [EventsOrderBy!], $filter: EventFilter) {
+	events(first: $first, offset: $offset, orderBy: $orderBy, filter: $filter) {
+		nodes {
+			data
+			blockNumber
+		}
+	}
+}";
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct Request {
+	pub query: &'static str,
+	pub variables: Value,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Response<D> {
+	pub data: Option<D>,
+	#[serde(default)]
+	pub errors: Vec<ResponseError>,
+}
+impl<D> Response<D> {
+	pub fn into_result(self) -> Result<D> {
+		if !self.errors.is_empty() {
+			anyhow::bail!(
+				"graphql responded with error(s)({})",
+				self.errors.into_iter().map(|e| e.message).collect::<Vec<_>>().join(", ")
+			);
+		}
+
+		self.data.ok_or_else(|| anyhow::anyhow!("graphql responded without data"))
+	}
+}
+#[test]
+fn response_into_result_should_work() {
+	let r = serde_json::from_str::<Response<Value>>(
+		r#"{"data":null,"errors":[{"message":"a"},{"message":"b","path":["events"]}]}"#,
+	)
+	.unwrap();
+
+	assert_eq!(r.into_result().unwrap_err().to_string(), "graphql responded with error(s)(a, b)");
+
+	let r = serde_json::from_str::<Response<Value>>(r#"{"data":{"events":[]}}"#).unwrap();
+
+	assert_eq!(r.into_result().unwrap(), json!({ "events": [] }));
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResponseError {
+	pub message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SquidEvents {
+	events: Vec<SquidEvent>,
+}
+#[derive(Debug, Deserialize)]
+struct SquidEvent {
+	args: Value,
+	block: SquidBlock,
+}
+#[derive(Debug, Deserialize)]
+struct SquidBlock {
+	height: BlockNumber,
+}
+
+#[derive(Debug, Deserialize)]
+struct SubqueryEvents {
+	events: SubqueryNodes,
+}
+#[derive(Debug, Deserialize)]
+struct SubqueryNodes {
+	nodes: Vec<SubqueryEvent>,
+}
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SubqueryEvent {
+	data: Value,
+	#[serde(deserialize_with = "util::deserialize_number")]
+	block_number: BlockNumber,
+}
+
+/// An event which has been normalized from the different schemas.
+#[derive(Debug)]
+pub struct Event {
+	pub args: Value,
+	pub height: BlockNumber,
+}
+
+// The args of `Auctions::BidAccepted`.
+//
+// Squids provide named args while SubQuery provides positional args.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum BidAcceptedArgs {
+	Named {
+		bidder: String,
+		#[serde(rename = "paraId")]
+		para_id: ParaId,
+		#[serde(deserialize_with = "util::deserialize_number")]
+		amount: Balance,
+		#[serde(rename = "firstSlot")]
+		first_slot: u32,
+		#[serde(rename = "lastSlot")]
+		last_slot: u32,
+	},
+	Positional(
+		String,
+		ParaId,
+		#[serde(deserialize_with = "util::deserialize_number")] Balance,
+		u32,
+		u32,
+	),
+}
+impl BidAcceptedArgs {
+	fn into_event(self) -> Result<EBidAccepted> {
+		let (bidder, para_id, amount, first_slot, last_slot) = match self {
+			Self::Named { bidder, para_id, amount, first_slot, last_slot } =>
+				(bidder, para_id, amount, first_slot, last_slot),
+			Self::Positional(bidder, para_id, amount, first_slot, last_slot) =>
+				(bidder, para_id, amount, first_slot, last_slot),
+		};
+
+		Ok(EBidAccepted {
+			bidder: array_bytes::hex2array(bidder)
+				.map_err(|e| anyhow::anyhow!("invalid bidder, {e:?}"))?,
+			para_id,
+			amount,
+			first_slot,
+			last_slot,
+		})
+	}
+}
+#[test]
+fn bid_accepted_args_should_work() {
+	let who = "0xd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d";
+	let named = serde_json::from_value::<BidAcceptedArgs>(json!({
+		"bidder": who,
+		"paraId": 2000,
+		"amount": "10000000000000",
+		"firstSlot": 0,
+		"lastSlot": 7,
+	}))
+	.unwrap()
+	.into_event()
+	.unwrap();
+	let positional =
+		serde_json::from_value::<BidAcceptedArgs>(json!([who, 2000, 10_000_000_000_000_u64, 0, 7]))
+			.unwrap()
+			.into_event()
+			.unwrap();
+
+	for e in [named, positional] {
+		assert_eq!(e.bidder, array_bytes::hex2array_unchecked::<_, 32>(who));
+		assert_eq!(e.para_id, 2000);
+		assert_eq!(e.amount, 10_000_000_000_000);
+		assert_eq!((e.first_slot, e.last_slot), (0, 7));
+	}
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Order {
+	Asc,
+	Desc,
+}
+
 #[derive(Debug)]
 pub struct Query<'a> {
-	pub limit: u32,
+	pub schema: GraphqlSchema,
+	/// Event name, e.g. `Auctions.BidAccepted`.
 	pub name: &'a str,
-	pub args_json_contains: Option<&'a str>,
+	pub args_contain: Option<Value>,
+	pub since_height: Option<BlockNumber>,
+	pub order: Order,
+	pub limit: u32,
+	pub offset: u32,
 }
-#[allow(unused)]
 impl<'a> Query<'a> {
-	pub fn new(name: &'a str) -> Self {
-		Self { limit: 1, name, args_json_contains: None }
+	pub fn new(schema: GraphqlSchema, name: &'a str) -> Self {
+		Self {
+			schema,
+			name,
+			args_contain: None,
+			since_height: None,
+			order: Order::Desc,
+			limit: 1,
+			offset: 0,
+		}
 	}
 
-	pub fn limit(mut self, limit: u32) -> Self {
-		self.limit = limit;
+	pub fn args_contain(mut self, args_contain: Value) -> Self {
+		self.args_contain = Some(args_contain);
 
 		self
 	}
 
-	pub fn name(mut self, name: &'a str) -> Self {
-		self.name = name;
+	pub fn since_height(mut self, since_height: BlockNumber) -> Self {
+		self.since_height = Some(since_height);
 
 		self
 	}
 
-	pub fn args_json_contains(mut self, args_json_contains: &'a str) -> Self {
-		self.args_json_contains = Some(args_json_contains);
+	pub fn order(mut self, order: Order) -> Self {
+		self.order = order;
 
 		self
 	}
-}
-impl<'a> Display for Query<'a> {
-	fn fmt(&self, f: &mut Formatter) -> FmtResult {
-		write!(
-			f,
-			"{{\
-				\"query\":\"{{\
-					events(\
-						limit:{},\
-						orderBy:block_id_DESC,\
-						where:{{\
-							name_eq:\\\"{}\\\"\
-							{}\
-						}}\
-					){{\
-						args,\
-						block{{height}}\
-					}}\
-				}}\"\
-			}}",
-			self.limit,
-			self.name,
-			if let Some(args_json_contains) = self.args_json_contains {
-				format!(",args_jsonContains:\\\"{}\\\"", args_json_contains)
-			} else {
-				"".into()
-			}
-		)
+
+	pub fn limit(mut self, limit: u32) -> Self {
+		self.limit = limit;
+
+		self
+	}
+
+	pub fn next_page(&mut self) {
+		self.offset += self.limit;
+	}
+
+	pub fn request(&self) -> Request {
+		match self.schema {
+			GraphqlSchema::Firesquid | GraphqlSchema::GiantSquid => {
+				let order_by = match (self.schema, self.order) {
+					(GraphqlSchema::Firesquid, Order::Asc) => "block_id_ASC",
+					(GraphqlSchema::Firesquid, Order::Desc) => "block_id_DESC",
+					(_, Order::Asc) => "block_height_ASC",
+					(_, Order::Desc) => "block_height_DESC",
+				};
+				let mut r#where = json!({ "name_eq": self.name });
+
+				if let Some(a) = &self.args_contain {
+					r#where["args_jsonContains"] = a.to_owned();
+				}
+				if let Some(h) = self.since_height {
+					r#where["block"] = json!({ "height_gte": h });
+				}
+
+				Request {
+					query: SQUID_EVENTS,
+					variables: json!({
+						"limit": self.limit,
+						"offset": self.offset,
+						"orderBy": [order_by],
+						"where": r#where,
+					}),
+				}
+			},
+			GraphqlSchema::Subquery => {
+				let (module, method) = self.name.split_once('.').unwrap_or((self.name, ""));
+				let mut filter = json!({
+					"module": { "equalTo": module.to_lowercase() },
+					"method": { "equalTo": method },
+				});
+
+				if let Some(a) = &self.args_contain {
+					filter["data"] = json!({ "contains": a });
+				}
+				if let Some(h) = self.since_height {
+					filter["blockNumber"] = json!({ "greaterThanOrEqualTo": h });
+				}
+
+				Request {
+					query: SUBQUERY_EVENTS,
+					variables: json!({
+						"first": self.limit,
+						"offset": self.offset,
+						"orderBy": [match self.order {
+							Order::Asc => "BLOCK_NUMBER_ASC",
+							Order::Desc => "BLOCK_NUMBER_DESC",
+						}],
+						"filter": filter,
+					}),
+				}
+			},
+		}
 	}
 }
 #[test]
-fn query_string_should_work() {
+fn query_request_should_work() {
+	let query = |schema| {
+		Query::new(schema, "Auctions.BidAccepted")
+			.args_contain(json!({ "bidder": "0x1234", "paraId": 2000 }))
+			.since_height(10)
+			.limit(100)
+	};
+
+	assert_eq!(
+		query(GraphqlSchema::Firesquid).request(),
+		Request {
+			query: SQUID_EVENTS,
+			variables: json!({
+				"limit": 100,
+				"offset": 0,
+				"orderBy": ["block_id_DESC"],
+				"where": {
+					"name_eq": "Auctions.BidAccepted",
+					"args_jsonContains": { "bidder": "0x1234", "paraId": 2000 },
+					"block": { "height_gte": 10 },
+				},
+			}),
+		}
+	);
+
+	let mut q = query(GraphqlSchema::GiantSquid).order(Order::Asc);
+
+	q.next_page();
+
+	assert_eq!(
+		q.request(),
+		Request {
+			query: SQUID_EVENTS,
+			variables: json!({
+				"limit": 100,
+				"offset": 100,
+				"orderBy": ["block_height_ASC"],
+				"where": {
+					"name_eq": "Auctions.BidAccepted",
+					"args_jsonContains": { "bidder": "0x1234", "paraId": 2000 },
+					"block": { "height_gte": 10 },
+				},
+			}),
+		}
+	);
 	assert_eq!(
-		Query::new("Auctions.BidAccepted")
-			.args_json_contains(
-				"{{\\\\\\\"bidder\\\\\\\":\\\\\\\"0x1234\\\\\\\",\\\\\\\"paraId\\\\\\\":2000}}",
-			)
-			.to_string(),
-		"{\"query\":\"{events(limit:1,orderBy:block_id_DESC,where:{name_eq:\\\"Auctions.BidAccepted\\\",args_jsonContains:\\\"{{\\\\\\\"bidder\\\\\\\":\\\\\\\"0x1234\\\\\\\",\\\\\\\"paraId\\\\\\\":2000}}\\\"}){args,block{height}}}\"}"
+		query(GraphqlSchema::Subquery).request(),
+		Request {
+			query: SUBQUERY_EVENTS,
+			variables: json!({
+				"first": 100,
+				"offset": 0,
+				"orderBy": ["BLOCK_NUMBER_DESC"],
+				"filter": {
+					"module": { "equalTo": "auctions" },
+					"method": { "equalTo": "BidAccepted" },
+					"data": { "contains": { "bidder": "0x1234", "paraId": 2000 } },
+					"blockNumber": { "greaterThanOrEqualTo": 10 },
+				},
+			}),
+		}
 	);
 }
 
 impl Hunter {
-	pub async fn query<'a, D>(&self, query: Query<'a>) -> Result<D>
+	async fn request<D>(&self, request: &Request) -> Result<D>
 	where
 		D: DeserializeOwned,
 	{
-		Ok(self
-			.http
-			.post(&self.configuration.graphql_endpoint)
-			.body(query.to_string())
+		let Some(uri) = &self.configuration.graphql_endpoint else {
+			anyhow::bail!("no graphql endpoint has been configured")
+		};
+
+		self.http
+			.post(uri)
+			.json(request)
 			.send()
 			.await?
-			.json()
-			.await?)
+			.error_for_status()?
+			.json::<Response<D>>()
+			.await?
+			.into_result()
+	}
+
+	/// Fetch a page of the events.
+	pub async fn query(&self, query: &Query<'_>) -> Result<Vec<Event>> {
+		let request = query.request();
+
+		Ok(match query.schema {
+			GraphqlSchema::Firesquid | GraphqlSchema::GiantSquid => self
+				.request::<SquidEvents>(&request)
+				.await?
+				.events
+				.into_iter()
+				.map(|e| Event { args: e.args, height: e.block.height })
+				.collect(),
+			GraphqlSchema::Subquery => self
+				.request::<SubqueryEvents>(&request)
+				.await?
+				.events
+				.nodes
+				.into_iter()
+				.map(|e| Event { args: e.data, height: e.block_number })
+				.collect(),
+		})
 	}
 
 	pub async fn last_accepted_bid_of(
 		&self,
-		who: &str,
+		who: &AccountId,
 		para_id: ParaId,
 	) -> Result<Option<AcceptedBid>> {
-		let mut events = self
-			.query::<EResponse<Event<EBidAccepted>>>(
-				Query::new("Auctions.BidAccepted").args_json_contains(&format!(
-					"{{\\\\\\\"bidder\\\\\\\":\\\\\\\"{who}\\\\\\\",\\\\\\\"paraId\\\\\\\":{para_id}}}",
-				)),
-			)
+		let who = array_bytes::bytes2hex("0x", who);
+		let args_contain = match self.configuration.graphql_schema {
+			GraphqlSchema::Firesquid | GraphqlSchema::GiantSquid =>
+				json!({ "bidder": who, "paraId": para_id }),
+			GraphqlSchema::Subquery => json!([who, para_id]),
+		};
+		let query = Query::new(self.configuration.graphql_schema, "Auctions.BidAccepted")
+			.args_contain(args_contain);
+
+		self.query(&query)
 			.await?
-			.data
-			.events;
-
-		if events.is_empty() {
-			Ok(None)
-		} else {
-			let e = events.remove(0);
-
-			Ok(Some(AcceptedBid {
-				at: e.block.height,
-				amount: e.args.amount.parse().expect("`amount` must be invalid digit"),
-				first_slot: e.args.first_slot,
-				last_slot: e.args.last_slot,
-			}))
+			.into_iter()
+			.next()
+			.map(|e| {
+				let b = serde_json::from_value::<BidAcceptedArgs>(e.args)?.into_event()?;
+
+				Ok(AcceptedBid {
+					at: e.height,
+					amount: b.amount,
+					first_slot: b.first_slot,
+					last_slot: b.last_slot,
+				})
+			})
+			.transpose()
+	}
+
+	/// Fill the index with the accepted bids since the start of the auction.
+	///
+	/// Return the height of the last indexed event, which is the height where the block scanning
+	/// can stop at.
+	pub async fn index_bids_from_graphql(
+		&self,
+		index: &mut BidIndex,
+		auction_index: u32,
+	) -> Result<Option<BlockNumber>> {
+		const PAGE_SIZE: u32 = 100;
+
+		let schema = self.configuration.graphql_schema;
+		let Some(started) =
+			self.query(&Query::new(schema, "Auctions.AuctionStarted")).await?.into_iter().next()
+		else {
+			return Ok(None);
+		};
+		let started_index = match &started.args {
+			Value::Object(a) => a.get("auctionIndex"),
+			Value::Array(a) => a.first(),
+			_ => None,
 		}
+		.and_then(Value::as_u64);
+
+		// The indexer hasn't reached the start of the auction yet.
+		if started_index != Some(auction_index as _) {
+			return Ok(None);
+		}
+
+		let mut query = Query::new(schema, "Auctions.BidAccepted")
+			.since_height(started.height)
+			.order(Order::Asc)
+			.limit(PAGE_SIZE);
+		let mut last = started.height;
+
+		loop {
+			let events = self.query(&query).await?;
+			let count = events.len();
+
+			for e in events {
+				index.insert(
+					e.height,
+					&serde_json::from_value::<BidAcceptedArgs>(e.args)?.into_event()?,
+				);
+
+				last = last.max(e.height);
+			}
+
+			if count < PAGE_SIZE as usize {
+				break;
+			}
+
+			query.next_page();
+		}
+
+		Ok(Some(last))
 	}
 }
@@ -0,0 +1,319 @@
+// std
+use std::{fs, path::PathBuf};
+// crates.io
+use app_dirs2::AppDataType;
+use serde::{Deserialize, Serialize};
+use subxt::config::polkadot::H256;
+// slothunter
+use crate::hunter::*;
+
+/// A local index of the `Auctions::BidAccepted` events of the current auction.
+///
+/// Only the latest accepted bid of each bidder is kept.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BidIndex {
+	#[serde(skip)]
+	pub path: PathBuf,
+	pub auction_index: u32,
+	/// Whether the blocks since the start of the auction have been fully scanned.
+	pub complete: bool,
+	/// The highest block which has been indexed.
+	pub synced_to: BlockNumber,
+	/// Blocks which the interrupted scans have indexed, the next scan skips them.
+	#[serde(default)]
+	pub scanned: Vec<ScannedRange>,
+	pub bids: Vec<IndexedBid>,
+}
+impl BidIndex {
+	pub fn load(genesis_hash: &H256) -> Result<Self> {
+		let path = app_dirs2::app_root(AppDataType::UserData, &SLOTHUNTER)?
+			.join(format!("bid-index-{}.json", array_bytes::bytes2hex("", &genesis_hash.0[..4])));
+		let mut index = if path.is_file() {
+			serde_json::from_str::<Self>(&fs::read_to_string(&path)?).unwrap_or_else(|e| {
+				tracing::warn!(
+					"discard the corrupted bid index({}) due to error({e})",
+					path.display()
+				);
+
+				Self::default()
+			})
+		} else {
+			Self::default()
+		};
+
+		index.path = path;
+
+		Ok(index)
+	}
+
+	pub fn save(&self) -> Result<()> {
+		fs::write(&self.path, serde_json::to_vec(self)?)?;
+
+		Ok(())
+	}
+
+	/// Reset the index if it doesn't belong to the given auction.
+	pub fn reset_to(&mut self, auction_index: u32) {
+		if self.auction_index != auction_index {
+			*self = Self { path: mem::take(&mut self.path), auction_index, ..Default::default() };
+		}
+	}
+
+	/// Record an accepted bid, the older ones will be ignored.
+	pub fn insert(&mut self, at: BlockNumber, e: &EBidAccepted) {
+		let bid = IndexedBid {
+			who: e.bidder,
+			para_id: e.para_id,
+			at,
+			amount: e.amount,
+			first_slot: e.first_slot,
+			last_slot: e.last_slot,
+		};
+
+		if let Some(b) = self.bids.iter_mut().find(|b| b.who == e.bidder && b.para_id == e.para_id)
+		{
+			if b.at <= at {
+				*b = bid;
+			}
+		} else {
+			self.bids.push(bid);
+		}
+	}
+
+	/// Record the block as scanned and join it with the adjacent ranges.
+	pub fn mark_scanned(&mut self, number: BlockNumber, parent_hash: H256) {
+		if let Some(r) = self.scanned.iter_mut().find(|r| r.tail == number + 1) {
+			r.tail = number;
+			r.parent_hash = parent_hash;
+		} else {
+			self.scanned.push(ScannedRange { head: number, tail: number, parent_hash });
+		}
+
+		// Keep the older range once the newer scan has reached it.
+		if let Some(i) = self.scanned.iter().position(|r| r.head + 1 == number) {
+			let older = self.scanned.swap_remove(i);
+
+			if let Some(r) = self.scanned.iter_mut().find(|r| r.tail == number) {
+				r.tail = older.tail;
+				r.parent_hash = older.parent_hash;
+			}
+		}
+	}
+
+	pub fn last_accepted_bid_of(&self, who: &AccountId, para_id: ParaId) -> Option<AcceptedBid> {
+		self.bids.iter().find(|b| &b.who == who && b.para_id == para_id).map(|b| AcceptedBid {
+			at: b.at,
+			amount: b.amount,
+			first_slot: b.first_slot,
+			last_slot: b.last_slot,
+		})
+	}
+}
+#[test]
+fn bid_index_should_work() {
+	let mut index = BidIndex::default();
+	let bid = |amount| EBidAccepted {
+		bidder: [1; 32],
+		para_id: 2000,
+		amount,
+		first_slot: 0,
+		last_slot: 1,
+	};
+
+	index.reset_to(1);
+	index.insert(10, &bid(5));
+	index.insert(9, &bid(4));
+
+	assert_eq!(index.last_accepted_bid_of(&[1; 32], 2000).unwrap().amount, 5);
+
+	index.insert(11, &bid(6));
+
+	assert_eq!(index.last_accepted_bid_of(&[1; 32], 2000).unwrap().amount, 6);
+	assert!(index.last_accepted_bid_of(&[1; 32], 2001).is_none());
+
+	index.reset_to(2);
+
+	assert!(index.bids.is_empty());
+}
+#[test]
+fn mark_scanned_should_work() {
+	let mut index = BidIndex::default();
+
+	// An interrupted scan from block(#20).
+	(18..=20).rev().for_each(|n| index.mark_scanned(n, H256::repeat_byte(n as _)));
+
+	// A resumed scan from block(#30), interrupted again.
+	(25..=30).rev().for_each(|n| index.mark_scanned(n, H256::repeat_byte(n as _)));
+
+	assert_eq!(index.scanned.len(), 2);
+
+	// The resumed scan reaches the older range.
+	(21..=24).rev().for_each(|n| index.mark_scanned(n, H256::repeat_byte(n as _)));
+
+	assert_eq!(index.scanned.len(), 1);
+	assert_eq!(
+		(index.scanned[0].head, index.scanned[0].tail, index.scanned[0].parent_hash),
+		(30, 18, H256::repeat_byte(18))
+	);
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScannedRange {
+	pub head: BlockNumber,
+	pub tail: BlockNumber,
+	/// Parent of the tail, where the scan continues.
+	pub parent_hash: H256,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IndexedBid {
+	#[serde(
+		serialize_with = "util::serialize_account_id",
+		deserialize_with = "util::deserialize_account_id"
+	)]
+	pub who: AccountId,
+	pub para_id: ParaId,
+	pub at: BlockNumber,
+	pub amount: Balance,
+	pub first_slot: u32,
+	pub last_slot: u32,
+}
+
+/// Maximum unindexed blocks which the scan of the startup goes through, about an hour of blocks.
+///
+/// The blocks are queued up meanwhile, so the rest is left to GraphQL and the later restarts.
+pub const BID_INDEX_SCAN_WINDOW: BlockNumber = 600;
+
+impl Hunter {
+	/// Scan back from `block_hash` to the start of the auction, or to the last indexed block.
+	///
+	/// At most `window` unindexed blocks are scanned if it's given.
+	///
+	/// This is best-effort, if the old blocks are unavailable, e.g. pruned, or the window has been
+	/// used up, the index stays partial.
+	pub async fn sync_bid_index(
+		&self,
+		index: &mut BidIndex,
+		block_hash: H256,
+		window: Option<BlockNumber>,
+	) -> Result<()> {
+		let Some(auction) = self.auction_at(&block_hash).await? else { return Ok(()) };
+
+		index.reset_to(auction.index);
+
+		let synced_to = if index.complete {
+			index.synced_to
+		} else if self.configuration.graphql_endpoint.is_some() {
+			match self.index_bids_from_graphql(index, auction.index).await {
+				Ok(h) => h.unwrap_or_default(),
+				Err(e) => {
+					tracing::warn!(
+						"failed to index the accepted bids from graphql due to error({e})"
+					);
+
+					0
+				},
+			}
+		} else {
+			0
+		};
+		let mut hash = block_hash;
+		let mut head = None;
+		let mut scanned = 0;
+
+		tracing::info!("indexing the accepted bids of auction(#{})", auction.index);
+
+		loop {
+			let (block, events) = match self.block_and_auction_events_of(hash).await {
+				Ok(r) => r,
+				Err(e) => {
+					tracing::warn!(
+						"  stop indexing at block({hash:?}) due to error({e}), the node might have \
+						pruned the old state, continue with a partial index of {} bidder(s)",
+						index.bids.len()
+					);
+
+					// Otherwise, the live blocks would move `synced_to` over the gap.
+					index.complete = false;
+					index.save()?;
+
+					return Ok(());
+				},
+			};
+			let number = block.number();
+
+			if number <= synced_to {
+				break;
+			}
+
+			head.get_or_insert(number);
+
+			// Jump over the blocks which an interrupted scan has indexed.
+			if let Some(r) = index.scanned.iter().find(|r| (r.tail..=r.head).contains(&number)) {
+				hash = r.parent_hash;
+
+				continue;
+			}
+
+			if window.is_some_and(|w| scanned >= w) {
+				tracing::warn!(
+					"  stop indexing at block(#{number}) after {scanned} block(s), continue with a \
+					partial index of {} bidder(s), the earlier bids fall back to graphql",
+					index.bids.len()
+				);
+
+				index.complete = false;
+				index.save()?;
+
+				return Ok(());
+			}
+
+			scanned += 1;
+
+			let mut started = false;
+
+			for e in events {
+				match e.event {
+					AuctionEvent::BidAccepted(e) => index.insert(number, &e),
+					AuctionEvent::AuctionStarted(e) if e.auction_index == auction.index =>
+						started = true,
+					_ => (),
+				}
+			}
+
+			if started || number == 0 {
+				break;
+			}
+
+			hash = block.header().parent_hash;
+			index.mark_scanned(number, hash);
+
+			if number % 1_000 == 0 {
+				tracing::info!("  indexed to block(#{number})");
+
+				if let Err(e) = index.save() {
+					tracing::warn!("failed to save the bid index due to error({e})");
+				}
+			}
+		}
+
+		index.complete = true;
+		index.synced_to = head.unwrap_or(synced_to);
+		index.scanned.clear();
+		index.save()?;
+
+		tracing::info!("  indexed {} bidder(s)", index.bids.len());
+
+		Ok(())
+	}
+
+	async fn block_and_auction_events_of(
+		&self,
+		hash: H256,
+	) -> Result<(Block, Vec<Phased<AuctionEvent>>)> {
+		let block = self.node.blocks().at(hash).await?;
+		let events = self.auction_events_of(&block).await?;
+
+		Ok((block, events))
+	}
+}
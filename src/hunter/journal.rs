@@ -0,0 +1,577 @@
+// std
+use std::{
+	fs::{File, OpenOptions},
+	io::{BufWriter, Write},
+	path::Path,
+	sync::Arc,
+};
+// crates.io
+use parquet::{
+	basic::Compression,
+	column::writer::ColumnWriterImpl,
+	data_type::{ByteArray, ByteArrayType, DataType, FixedLenByteArrayType, Int32Type},
+	file::{properties::WriterProperties, writer::SerializedFileWriter},
+	schema::parser,
+};
+use serde::{Deserialize, Serialize};
+use subxt::config::polkadot::H256;
+// slothunter
+use crate::hunter::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RecordFormat {
+	Csv,
+	JsonLines,
+	/// Only for the export, since a Parquet file can't be appended.
+	Parquet,
+}
+impl RecordFormat {
+	/// Detect the format from the extension, the unsupported ones are rejected.
+	pub fn of(path: &Path) -> Result<Self> {
+		match path.extension().and_then(|e| e.to_str()) {
+			Some("csv") => Ok(Self::Csv),
+			Some("jsonl" | "ndjson") => Ok(Self::JsonLines),
+			Some("parquet") => Ok(Self::Parquet),
+			e => Err(Error::Configuration(format!(
+				"unsupported extension({}) of {}, use `.csv`, `.jsonl` or `.parquet`, or specify the \
+				format",
+				e.unwrap_or_default(),
+				path.display()
+			)))?,
+		}
+	}
+}
+#[test]
+fn record_format_of_should_work() {
+	assert_eq!(RecordFormat::of(Path::new("history.csv")).unwrap(), RecordFormat::Csv);
+	assert_eq!(RecordFormat::of(Path::new("history.jsonl")).unwrap(), RecordFormat::JsonLines);
+	assert_eq!(RecordFormat::of(Path::new("history.parquet")).unwrap(), RecordFormat::Parquet);
+	assert!(RecordFormat::of(Path::new("history.xlsx")).is_err());
+	assert!(RecordFormat::of(Path::new("history")).is_err());
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RecordKind {
+	/// An entry of the `Auctions::Winning` array.
+	Winning,
+	/// A winner calculated by `Winning::result`.
+	Winner,
+	/// The threshold calculated by `Winning::result`.
+	Threshold,
+	Bidder,
+	/// An action taken by Slothunter.
+	Action,
+	/// An accepted bid of Slothunter.
+	BidAccepted,
+	/// A request or a decision of the approval.
+	Approval,
+}
+impl RecordKind {
+	fn as_str(&self) -> &'static str {
+		match self {
+			Self::Winning => "winning",
+			Self::Winner => "winner",
+			Self::Threshold => "threshold",
+			Self::Bidder => "bidder",
+			Self::Action => "action",
+			Self::BidAccepted => "bid-accepted",
+			Self::Approval => "approval",
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Record {
+	pub auction_index: u32,
+	pub block_height: BlockNumber,
+	pub block_hash: H256,
+	pub kind: RecordKind,
+	pub who: Option<String>,
+	pub para_id: Option<ParaId>,
+	pub first_slot: Option<u32>,
+	pub last_slot: Option<u32>,
+	pub amount: Option<Balance>,
+	pub note: Option<String>,
+}
+impl Record {
+	const CSV_HEADER: &'static str =
+		"auction_index,block_height,block_hash,kind,who,para_id,first_slot,last_slot,amount,note";
+	const PARQUET_SCHEMA: &'static str = "
+		message record {
+			REQUIRED INT32 auction_index (INTEGER(32, false));
+			REQUIRED INT32 block_height (INTEGER(32, false));
+			REQUIRED BYTE_ARRAY block_hash (UTF8);
+			REQUIRED BYTE_ARRAY kind (UTF8);
+			OPTIONAL BYTE_ARRAY who (UTF8);
+			OPTIONAL INT32 para_id (INTEGER(32, false));
+			OPTIONAL INT32 first_slot (INTEGER(32, false));
+			OPTIONAL INT32 last_slot (INTEGER(32, false));
+			OPTIONAL FIXED_LEN_BYTE_ARRAY (16) amount (DECIMAL(38, 0));
+			OPTIONAL BYTE_ARRAY note (UTF8);
+		}
+	";
+
+	pub fn new(
+		auction_index: u32,
+		block_height: BlockNumber,
+		block_hash: H256,
+		kind: RecordKind,
+	) -> Self {
+		Self {
+			auction_index,
+			block_height,
+			block_hash,
+			kind,
+			who: None,
+			para_id: None,
+			first_slot: None,
+			last_slot: None,
+			amount: None,
+			note: None,
+		}
+	}
+
+	pub fn who(mut self, who: &AccountId, para_id: ParaId) -> Self {
+		self.who = Some(util::ss58(who));
+		self.para_id = Some(para_id);
+
+		self
+	}
+
+	pub fn leases(mut self, leases: SlotRange) -> Self {
+		self.first_slot = Some(leases.0);
+		self.last_slot = Some(leases.1);
+
+		self
+	}
+
+	pub fn amount(mut self, amount: Balance) -> Self {
+		self.amount = Some(amount);
+
+		self
+	}
+
+	pub fn note(mut self, note: impl Into<String>) -> Self {
+		self.note = Some(note.into());
+
+		self
+	}
+
+	pub fn to_csv(&self) -> String {
+		fn opt<T>(v: &Option<T>) -> String
+		where
+			T: ToString,
+		{
+			v.as_ref().map(ToString::to_string).unwrap_or_default()
+		}
+
+		[
+			self.auction_index.to_string(),
+			self.block_height.to_string(),
+			format!("{:?}", self.block_hash),
+			self.kind.as_str().into(),
+			opt(&self.who),
+			opt(&self.para_id),
+			opt(&self.first_slot),
+			opt(&self.last_slot),
+			opt(&self.amount),
+			self.note
+				.as_ref()
+				.map(|n| format!("\"{}\"", n.trim().replace('"', "\"\"")))
+				.unwrap_or_default(),
+		]
+		.join(",")
+	}
+}
+#[test]
+fn record_to_csv_should_work() {
+	let record = Record::new(1, 2, H256::zero(), RecordKind::Action)
+		.who(&[0; 32], 2000)
+		.leases((3, 4))
+		.amount(5)
+		.note("bid with \"KSM(1)\"");
+
+	assert_eq!(
+		record.to_csv(),
+		"1,2,0x0000000000000000000000000000000000000000000000000000000000000000,action,5C4hrfjw9DjXZTzV3MwzrrAr9P1MJhSrvWGWqi1eSuyUpnhM,2000,3,4,5,\"bid with \"\"KSM(1)\"\"\""
+	);
+	assert_eq!(
+		Record::new(1, 2, H256::zero(), RecordKind::Threshold).to_csv(),
+		"1,2,0x0000000000000000000000000000000000000000000000000000000000000000,threshold,,,,,,"
+	);
+}
+
+/// Append records to a CSV or a JSON Lines file, or write them to a new Parquet file.
+#[derive(Debug)]
+pub struct RecordWriter {
+	writer: Writer,
+}
+impl RecordWriter {
+	/// Rows of a Parquet row group, the records are buffered until then.
+	const PARQUET_ROW_GROUP_SIZE: usize = 64 * 1_024;
+
+	pub fn open(path: &Path, format: RecordFormat) -> Result<Self> {
+		if format == RecordFormat::Parquet {
+			let schema = Arc::new(parser::parse_message_type(Record::PARQUET_SCHEMA)?);
+			let properties =
+				Arc::new(WriterProperties::builder().set_compression(Compression::SNAPPY).build());
+			let writer = SerializedFileWriter::new(File::create(path)?, schema, properties)?;
+
+			return Ok(Self { writer: Writer::Parquet(writer, Vec::new()) });
+		}
+
+		let file = OpenOptions::new().create(true).append(true).open(path)?;
+		let is_empty = file.metadata()?.len() == 0;
+		let mut writer = BufWriter::new(file);
+
+		if is_empty && format == RecordFormat::Csv {
+			writeln!(writer, "{}", Record::CSV_HEADER)?;
+		}
+
+		Ok(Self {
+			writer: if format == RecordFormat::Csv {
+				Writer::Csv(writer)
+			} else {
+				Writer::JsonLines(writer)
+			},
+		})
+	}
+
+	pub fn write(&mut self, records: &[Record]) -> Result<()> {
+		match &mut self.writer {
+			Writer::Csv(w) => {
+				for r in records {
+					writeln!(w, "{}", r.to_csv())?;
+				}
+
+				w.flush()?;
+			},
+			Writer::JsonLines(w) => {
+				for r in records {
+					serde_json::to_writer(&mut *w, r)?;
+					writeln!(w)?;
+				}
+
+				w.flush()?;
+			},
+			Writer::Parquet(w, buffer) => {
+				buffer.extend_from_slice(records);
+
+				if buffer.len() >= Self::PARQUET_ROW_GROUP_SIZE {
+					write_row_group(w, &mem::take(buffer))?;
+				}
+			},
+		}
+
+		Ok(())
+	}
+
+	/// Finish the file, a Parquet file is unreadable until its footer is written.
+	pub fn close(self) -> Result<()> {
+		match self.writer {
+			Writer::Csv(mut w) | Writer::JsonLines(mut w) => w.flush()?,
+			Writer::Parquet(mut w, buffer) => {
+				write_row_group(&mut w, &buffer)?;
+				w.close()?;
+			},
+		}
+
+		Ok(())
+	}
+}
+
+#[derive(Debug)]
+enum Writer {
+	Csv(BufWriter<File>),
+	JsonLines(BufWriter<File>),
+	/// The writer and the records of the next row group.
+	Parquet(SerializedFileWriter<File>, Vec<Record>),
+}
+
+/// Write the records as a row group, the columns are in the order of [`Record::PARQUET_SCHEMA`].
+fn write_row_group(writer: &mut SerializedFileWriter<File>, records: &[Record]) -> Result<()> {
+	fn u32_of(v: u32) -> Option<i32> {
+		// Reinterpreted as unsigned by the `INTEGER(32, false)` annotation.
+		Some(v as _)
+	}
+	fn utf8_of(v: &str) -> Option<ByteArray> {
+		Some(v.into())
+	}
+	fn column<T>(
+		writer: &mut ColumnWriterImpl<T>,
+		values: impl Iterator<Item = Option<T::T>>,
+	) -> Result<()>
+	where
+		T: DataType,
+	{
+		let (mut levels, mut present) = (Vec::new(), Vec::new());
+
+		for v in values {
+			levels.push(v.is_some() as i16);
+			present.extend(v);
+		}
+
+		// The definition levels are only for the optional columns.
+		let levels = (writer.get_descriptor().max_def_level() > 0).then_some(levels);
+
+		writer.write_batch(&present, levels.as_deref(), None)?;
+
+		Ok(())
+	}
+
+	if records.is_empty() {
+		return Ok(());
+	}
+
+	let mut row_group = writer.next_row_group()?;
+	let mut i = 0;
+
+	while let Some(mut c) = row_group.next_column()? {
+		let rs = records.iter();
+
+		match i {
+			0 => column(c.typed::<Int32Type>(), rs.map(|r| u32_of(r.auction_index)))?,
+			1 => column(c.typed::<Int32Type>(), rs.map(|r| u32_of(r.block_height)))?,
+			2 => column(
+				c.typed::<ByteArrayType>(),
+				rs.map(|r| utf8_of(&format!("{:?}", r.block_hash))),
+			)?,
+			3 => column(c.typed::<ByteArrayType>(), rs.map(|r| utf8_of(r.kind.as_str())))?,
+			4 =>
+				column(c.typed::<ByteArrayType>(), rs.map(|r| r.who.as_deref().and_then(utf8_of)))?,
+			5 => column(c.typed::<Int32Type>(), rs.map(|r| r.para_id.and_then(u32_of)))?,
+			6 => column(c.typed::<Int32Type>(), rs.map(|r| r.first_slot.and_then(u32_of)))?,
+			7 => column(c.typed::<Int32Type>(), rs.map(|r| r.last_slot.and_then(u32_of)))?,
+			// `DECIMAL(38, 0)` in big-endian, which holds any realistic balance.
+			8 => column(
+				c.typed::<FixedLenByteArrayType>(),
+				rs.map(|r| r.amount.map(|a| (a as i128).to_be_bytes().to_vec().into())),
+			)?,
+			9 => column(
+				c.typed::<ByteArrayType>(),
+				rs.map(|r| r.note.as_deref().map(str::trim).and_then(utf8_of)),
+			)?,
+			_ => unreachable!("the schema has 10 columns"),
+		}
+
+		c.close()?;
+
+		i += 1;
+	}
+
+	row_group.close()?;
+
+	Ok(())
+}
+#[test]
+fn parquet_record_writer_should_work() {
+	// crates.io
+	use parquet::{
+		file::reader::{FileReader, SerializedFileReader},
+		record::RowAccessor,
+	};
+
+	let path = std::env::temp_dir().join(format!("slothunter-{}.parquet", std::process::id()));
+	let mut writer = RecordWriter::open(&path, RecordFormat::Parquet).unwrap();
+
+	writer
+		.write(&[
+			Record::new(1, 2, H256::zero(), RecordKind::Action)
+				.who(&[0; 32], 2000)
+				.leases((3, 4))
+				.amount(5_000_000_000_000)
+				.note("bid with \"KSM(5)\""),
+			Record::new(1, 2, H256::zero(), RecordKind::Threshold),
+		])
+		.unwrap();
+	writer.write(&[Record::new(1, 3, H256::zero(), RecordKind::Bidder)]).unwrap();
+	writer.close().unwrap();
+
+	let reader = SerializedFileReader::new(File::open(&path).unwrap()).unwrap();
+	let rows = reader.get_row_iter(None).unwrap().map(Result::unwrap).collect::<Vec<_>>();
+
+	std::fs::remove_file(&path).unwrap();
+
+	assert_eq!(reader.metadata().num_row_groups(), 1);
+	assert_eq!(rows.len(), 3);
+	assert_eq!(rows[0].get_uint(1).unwrap(), 2);
+	assert_eq!(rows[0].get_string(3).unwrap(), "action");
+	assert_eq!(rows[0].get_uint(5).unwrap(), 2000);
+	assert_eq!(
+		rows[0].get_decimal(8).unwrap().data(),
+		5_000_000_000_000_i128.to_be_bytes().as_slice()
+	);
+	assert_eq!(rows[0].get_string(9).unwrap(), "bid with \"KSM(5)\"");
+	assert!(rows[1].get_string(4).is_err());
+	assert_eq!(rows[2].get_string(3).unwrap(), "bidder");
+}
+
+impl Record {
+	pub fn of_winning(
+		auction_index: u32,
+		block_height: BlockNumber,
+		block_hash: H256,
+		winning: &Winning,
+	) -> Vec<Self> {
+		winning
+			.0
+			.iter()
+			.filter_map(Option::as_ref)
+			.map(|w| {
+				Self::new(auction_index, block_height, block_hash, RecordKind::Winning)
+					.who(&w.who, w.para_id)
+					.leases(w.leases)
+					.amount(w.value)
+			})
+			.collect()
+	}
+
+	pub fn of_winners(
+		auction_index: u32,
+		block_height: BlockNumber,
+		block_hash: H256,
+		winners: &[Winner],
+		threshold: Balance,
+	) -> Vec<Self> {
+		winners
+			.iter()
+			.map(|w| {
+				Self::new(auction_index, block_height, block_hash, RecordKind::Winner)
+					.who(&w.who, w.para_id)
+					.leases(w.leases)
+					.amount(w.value)
+			})
+			.chain([Self::new(auction_index, block_height, block_hash, RecordKind::Threshold)
+				.amount(threshold)])
+			.collect()
+	}
+
+	pub fn of_bidder(
+		auction_index: u32,
+		block_height: BlockNumber,
+		block_hash: H256,
+		bidder: &Bidder,
+	) -> Self {
+		let r = Self::new(auction_index, block_height, block_hash, RecordKind::Bidder)
+			.who(&bidder.who, bidder.para_id)
+			.amount(bidder.reserved);
+
+		if let Some(b) = &bidder.last_accepted_bid {
+			r.leases((b.first_slot, b.last_slot))
+				.note(format!("last accepted bid {} at block(#{})", b.amount, b.at))
+		} else {
+			r
+		}
+	}
+}
+
+impl Hunter {
+	/// Export the records of every block within the given range.
+	///
+	/// The range defaults to the ending period of the current auction.
+	///
+	/// The bidders are seeded from the saved bid index, but only with the bids before the range.
+	/// A bidder whose latest indexed bid is within or after the range is missing until it bids
+	/// again within the range.
+	pub async fn export(
+		&mut self,
+		from: Option<BlockNumber>,
+		to: Option<BlockNumber>,
+		path: &Path,
+		format: RecordFormat,
+	) -> Result<()> {
+		let head = self.node.blocks().at_latest().await?;
+
+		self.auction_ending_period = self.auction_ending_period()?;
+		self.auction_sample_length = self.auction_sample_length()?;
+		self.bidder = self.bidder_at(&head.hash()).await?;
+
+		let (from, to) = match (from, to) {
+			(Some(f), Some(t)) => (f, t),
+			(f, t) => {
+				let Some(a) = self.auction_at(&head.hash()).await? else {
+					anyhow::bail!("no auction is active, please specify the block range")
+				};
+
+				(
+					f.unwrap_or(a.ending_period_start_at),
+					t.unwrap_or(a.ending_period_start_at + self.auction_ending_period),
+				)
+			},
+		};
+		let to = to.min(head.number());
+
+		if from > to {
+			Err(Error::Configuration(format!(
+				"block range(#{from}, #{to}) is empty, `from` must not be greater than `to` or the \
+				 latest block"
+			)))?;
+		}
+
+		let mut writer = RecordWriter::open(path, format)?;
+		let mut index = BidIndex::load(&self.node.genesis_hash())?;
+
+		// The index only keeps the latest bid of each bidder, the later ones are ahead of the
+		// range.
+		index.bids.retain(|b| b.at < from);
+
+		tracing::info!("exporting block range(#{from}, #{to}) to {}", path.display());
+
+		for n in from..=to {
+			let Some(hash) = self.block_hash_of(n).await? else { continue };
+			let block = self.node.blocks().at(hash).await?;
+			let mut records = Vec::new();
+
+			for e in self.auction_events_of(&block).await? {
+				match e.event {
+					AuctionEvent::AuctionStarted(e) => index.reset_to(e.auction_index),
+					AuctionEvent::BidAccepted(e) => {
+						if self.is_bidder(&e.bidder, e.para_id) {
+							if let Some(a) = self.auction_at(&hash).await? {
+								records.push(
+									Record::new(a.index, n, hash, RecordKind::BidAccepted)
+										.who(&e.bidder, e.para_id)
+										.leases((e.first_slot, e.last_slot))
+										.amount(e.amount),
+								);
+							}
+						}
+
+						index.insert(n, &e);
+					},
+					_ => (),
+				}
+			}
+
+			let Some(auction) = self.auction_at(&hash).await? else { continue };
+
+			index.reset_to(auction.index);
+
+			self.bidders_at(&hash, &index)
+				.await?
+				.iter()
+				.for_each(|b| records.push(Record::of_bidder(auction.index, n, hash, b)));
+
+			if let Some(w) = self.winning_at(&hash, n, auction.ending_period_start_at).await? {
+				let (winners, threshold) = w.result();
+
+				records.extend(Record::of_winning(auction.index, n, hash, &w));
+				records.extend(Record::of_winners(auction.index, n, hash, &winners, threshold));
+			}
+
+			writer.write(&records)?;
+
+			if n % 100 == 0 {
+				tracing::info!("  exported to block(#{n})");
+			}
+		}
+
+		writer.close()?;
+
+		tracing::info!("exported block range(#{from}, #{to})");
+
+		Ok(())
+	}
+}
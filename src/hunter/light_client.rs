@@ -0,0 +1,123 @@
+// std
+use std::{fs, num::NonZeroU32};
+// crates.io
+use futures::stream;
+use subxt::{
+	backend::rpc::{RawRpcFuture, RawRpcSubscription, RawValue, RpcClientT},
+	error::RpcError,
+};
+use subxt_lightclient::smoldot::{AddChainConfig, AddChainConfigJsonRpc};
+// slothunter
+use crate::hunter::*;
+
+/// The special node endpoint which indicates to use the embedded light client.
+pub const LIGHT_CLIENT: &str = "light-client";
+
+fn rpc_error(e: impl ToString) -> RpcError {
+	RpcError::ClientError(Box::new(Error::Transient(e.to_string())))
+}
+
+/// Embedded smoldot light client, which can be used as the RPC client of subxt.
+#[derive(Clone)]
+pub struct LightClientRpc(subxt_lightclient::LightClientRpc);
+impl LightClientRpc {
+	/// Start the light client from the configured chain spec.
+	pub fn new(configuration: &LightClient) -> Result<Self> {
+		let mut chain_spec = serde_json::from_str::<serde_json::Value>(&fs::read_to_string(
+			&configuration.chain_spec,
+		)?)
+		.map_err(|e| {
+			Error::Configuration(format!(
+				"invalid chain spec({}), {e}",
+				configuration.chain_spec.display()
+			))
+		})?;
+
+		if !configuration.bootnodes.is_empty() {
+			chain_spec["bootNodes"] = configuration.bootnodes.clone().into();
+		}
+
+		let rpc = subxt_lightclient::LightClientRpc::new(AddChainConfig {
+			specification: &chain_spec.to_string(),
+			json_rpc: AddChainConfigJsonRpc::Enabled {
+				max_pending_requests: NonZeroU32::new(128).expect("128 is non-zero; qed"),
+				max_subscriptions: 1024,
+			},
+			potential_relay_chains: [].into_iter(),
+			database_content: "",
+			user_data: (),
+		})
+		.map_err(|e| Error::Configuration(e.to_string()))?;
+
+		Ok(Self(rpc))
+	}
+}
+impl RpcClientT for LightClientRpc {
+	fn request_raw<'a>(
+		&'a self,
+		method: &'a str,
+		params: Option<Box<RawValue>>,
+	) -> RawRpcFuture<'a, Box<RawValue>> {
+		Box::pin(async move {
+			let params = params.map(|p| p.get().to_owned()).unwrap_or_else(|| "[]".into());
+			let response = self
+				.0
+				.method_request(method.into(), params)
+				.map_err(|_| rpc_error("light client has been stopped"))?
+				.await
+				.map_err(|_| rpc_error("light client has been stopped"))?;
+
+			response.map_err(rpc_error)
+		})
+	}
+
+	fn subscribe_raw<'a>(
+		&'a self,
+		sub: &'a str,
+		params: Option<Box<RawValue>>,
+		_unsub: &'a str,
+	) -> RawRpcFuture<'a, RawRpcSubscription> {
+		Box::pin(async move {
+			let params = params.map(|p| p.get().to_owned()).unwrap_or_else(|| "[]".into());
+			let (id, notifications) = self
+				.0
+				.subscription_request(sub.into(), params)
+				.map_err(|_| rpc_error("light client has been stopped"))?;
+			let id = id
+				.await
+				.map_err(|_| rpc_error("light client has been stopped"))?
+				.map_err(rpc_error)?;
+			let stream =
+				stream::unfold(
+					notifications,
+					|mut n| async move { n.recv().await.map(|v| (Ok(v), n)) },
+				);
+
+			Ok(RawRpcSubscription {
+				stream: Box::pin(stream),
+				id: Some(id.get().trim_matches('"').into()),
+			})
+		})
+	}
+}
+
+#[cfg(feature = "node-test")]
+#[tokio::test]
+async fn light_client_should_work() {
+	use subxt::backend::legacy::LegacyRpcMethods;
+
+	// The local node's key is `0x0000000000000000000000000000000000000000000000000000000000000001`.
+	let rpc = LightClientRpc::new(&LightClient {
+		chain_spec: "test/integration/data/rococo.json.fork-off".into(),
+		bootnodes: vec![
+			"/ip4/127.0.0.1/tcp/30333/p2p/12D3KooWEyoppNCUx8Yx66oV9fJnriXwCcXwDDUA2kj6vnc6iDEp"
+				.into(),
+		],
+	})
+	.unwrap();
+	let rpc = RpcClient::new(rpc);
+	let node = OnlineClient::<PolkadotConfig>::from_rpc_client(rpc.clone()).await.unwrap();
+
+	assert!(LegacyRpcMethods::<PolkadotConfig>::new(rpc).system_chain().await.is_ok());
+	assert!(node.blocks().at_latest().await.is_ok());
+}
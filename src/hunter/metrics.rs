@@ -0,0 +1,201 @@
+// crates.io
+use prometheus::{
+	core::Collector, Encoder, Gauge, HistogramOpts, HistogramTimer, HistogramVec, IntCounter,
+	IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+// slothunter
+use crate::hunter::*;
+
+#[derive(Debug)]
+pub struct Metrics {
+	registry: Registry,
+	pub block_height: IntGauge,
+	pub blocks_behind_head: IntGauge,
+	pub auction_is_open: IntGauge,
+	pub ending_period_remaining_seconds: IntGauge,
+	pub bid_amount: Gauge,
+	pub minimum_bid_to_win: Gauge,
+	pub upper_limit_gap: Gauge,
+	pub tender_attempts: IntCounter,
+	pub tender_successes: IntCounter,
+	pub tender_failures: IntCounter,
+	pub notification_deliveries: IntCounterVec,
+	pub notification_failures: IntCounterVec,
+	pub ws_reconnects: IntCounter,
+	pub endpoint_down: IntGaugeVec,
+	pub rpc_duration: HistogramVec,
+}
+impl Metrics {
+	pub fn encode(&self) -> Vec<u8> {
+		let mut buffer = Vec::new();
+
+		TextEncoder::new()
+			.encode(&self.registry.gather(), &mut buffer)
+			.expect("encoding into a vec never fails");
+
+		buffer
+	}
+
+	/// Start a timer which records the latency of the RPC query on drop.
+	pub fn rpc_timer(&self, query: &str) -> HistogramTimer {
+		self.rpc_duration.with_label_values(&[query]).start_timer()
+	}
+}
+impl Default for Metrics {
+	fn default() -> Self {
+		fn register<C>(registry: &Registry, collector: C) -> C
+		where
+			C: 'static + Clone + Collector,
+		{
+			registry.register(Box::new(collector.clone())).expect("metric must be unique");
+
+			collector
+		}
+
+		let registry =
+			Registry::new_custom(Some("slothunter".into()), None).expect("prefix must be valid");
+		let int_gauge = |name, help| {
+			register(&registry, IntGauge::new(name, help).expect("metric must be valid"))
+		};
+		let gauge =
+			|name, help| register(&registry, Gauge::new(name, help).expect("metric must be valid"));
+		let int_counter = |name, help| {
+			register(&registry, IntCounter::new(name, help).expect("metric must be valid"))
+		};
+
+		Self {
+			block_height: int_gauge("block_height", "Height of the last processed block."),
+			blocks_behind_head: int_gauge(
+				"blocks_behind_head",
+				"Number of blocks between the last processed block and the best block.",
+			),
+			auction_is_open: int_gauge("auction_is_open", "Whether an auction is open."),
+			ending_period_remaining_seconds: int_gauge(
+				"ending_period_remaining_seconds",
+				"Approximate remaining time of the auction's ending period.",
+			),
+			bid_amount: gauge("bid_amount", "Amount of our last accepted bid, in the token unit."),
+			minimum_bid_to_win: gauge(
+				"minimum_bid_to_win",
+				"Minimum bid to win the configured leases, in the token unit.",
+			),
+			upper_limit_gap: gauge(
+				"upper_limit_gap",
+				"Gap between the upper limit and the minimum bid to win, in the token unit.",
+			),
+			tender_attempts: int_counter("tender_attempts_total", "Number of tender attempts."),
+			tender_successes: int_counter(
+				"tender_successes_total",
+				"Number of successful tenders.",
+			),
+			tender_failures: int_counter("tender_failures_total", "Number of failed tenders."),
+			notification_deliveries: register(
+				&registry,
+				IntCounterVec::new(
+					Opts::new(
+						"notification_deliveries_total",
+						"Number of successful notification deliveries.",
+					),
+					&["channel"],
+				)
+				.expect("metric must be valid"),
+			),
+			notification_failures: register(
+				&registry,
+				IntCounterVec::new(
+					Opts::new(
+						"notification_failures_total",
+						"Number of notification deliveries which have failed after all the retries.",
+					),
+					&["channel"],
+				)
+				.expect("metric must be valid"),
+			),
+			ws_reconnects: int_counter(
+				"ws_reconnects_total",
+				"Number of the websocket reconnections.",
+			),
+			endpoint_down: register(
+				&registry,
+				IntGaugeVec::new(
+					Opts::new(
+						"endpoint_down",
+						"Whether the block subscription of the node endpoint keeps failing.",
+					),
+					&["endpoint"],
+				)
+				.expect("metric must be valid"),
+			),
+			rpc_duration: register(
+				&registry,
+				HistogramVec::new(
+					HistogramOpts::new("rpc_duration_seconds", "Latency of the RPC queries."),
+					&["query"],
+				)
+				.expect("metric must be valid"),
+			),
+			registry,
+		}
+	}
+}
+#[test]
+fn metrics_encode_should_work() {
+	let metrics = Metrics::default();
+
+	metrics.block_height.set(10);
+	metrics.notification_failures.with_label_values(&["webhook"]).inc();
+	drop(metrics.rpc_timer("auction_at"));
+
+	let encoded = String::from_utf8(metrics.encode()).unwrap();
+
+	assert!(encoded.contains("slothunter_block_height 10"));
+	assert!(encoded.contains("slothunter_notification_failures_total{channel=\"webhook\"} 1"));
+	assert!(encoded.contains("slothunter_rpc_duration_seconds_count{query=\"auction_at\"} 1"));
+}
+
+impl Hunter {
+	/// Update the gauges, a failure is logged since the metrics must not stop the hunting.
+	pub(super) async fn measure(&self, state: &State) {
+		// Nobody can scrape them without the API.
+		if self.configuration.api.is_none() {
+			return;
+		}
+
+		let token = &self.token;
+		let metrics = &self.metrics;
+
+		metrics.block_height.set(state.block_height as _);
+
+		match self.best_block_number().await {
+			Ok(head) =>
+				metrics.blocks_behind_head.set(head.saturating_sub(state.block_height) as _),
+			Err(e) => tracing::warn!("failed to measure the blocks behind head due to error({e})"),
+		}
+		metrics.auction_is_open.set(state.auction_is_open as _);
+		metrics.ending_period_remaining_seconds.set(
+			state
+				.auction
+				.as_ref()
+				.map(|a| {
+					util::blocks2seconds(
+						(a.ending_period_start_at + self.auction_ending_period)
+							.saturating_sub(state.block_height.max(a.ending_period_start_at)),
+					)
+				})
+				.unwrap_or_default() as _,
+		);
+		metrics.bid_amount.set(token.to_f64(state.bid_amount));
+
+		let status = self.status.read().expect("lock must not be poisoned");
+
+		if let Some(b) = status.minimum_bid_to_win {
+			metrics.minimum_bid_to_win.set(token.to_f64(b));
+			metrics
+				.upper_limit_gap
+				.set(token.to_f64(self.configuration.bid.upper_limit) - token.to_f64(b));
+		} else {
+			metrics.minimum_bid_to_win.set(0.);
+			metrics.upper_limit_gap.set(token.to_f64(self.configuration.bid.upper_limit));
+		}
+	}
+}
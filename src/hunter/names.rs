@@ -0,0 +1,175 @@
+// std
+use std::{
+	collections::HashMap,
+	sync::{Arc, Mutex, RwLock},
+};
+// crates.io
+use scale_value::ValueDef;
+use subxt::dynamic::{self, At, Value};
+// slothunter
+use crate::hunter::*;
+
+/// Display names of the accounts and the parachains.
+///
+/// The clones share the names, e.g. the bot renders the same names as the notifications.
+#[derive(Clone, Debug, Default)]
+pub struct Names {
+	paras: Arc<RwLock<HashMap<ParaId, String>>>,
+	/// Cache of the on-chain identities, `None` if the account has no identity.
+	identities: Arc<Mutex<HashMap<AccountId, Option<String>>>>,
+}
+impl Names {
+	pub fn new(naming: &Naming) -> Self {
+		Self { paras: Arc::new(RwLock::new(naming.paras.clone())), identities: Arc::default() }
+	}
+
+	/// Apply the new naming configuration, the cached identities will be kept if still enabled.
+	pub fn reset(&self, naming: &Naming) {
+		// The names are always consistent, ignore the poisoning.
+		*self.paras.write().unwrap_or_else(|e| e.into_inner()) = naming.paras.clone();
+
+		if !naming.identity {
+			self.identities_mut().clear();
+		}
+	}
+
+	fn identities_mut(&self) -> std::sync::MutexGuard<'_, HashMap<AccountId, Option<String>>> {
+		// The cache is always consistent, ignore the poisoning.
+		self.identities.lock().unwrap_or_else(|e| e.into_inner())
+	}
+
+	/// Return the SS58 address with the identity if available, e.g. `Alice (5Grw...)`.
+	pub fn account(&self, who: &AccountId) -> String {
+		let address = util::ss58(who);
+
+		match self.identities_mut().get(who) {
+			Some(Some(n)) => format!("{n} ({address})"),
+			_ => address,
+		}
+	}
+
+	/// Return the para id with the project name if available, e.g. `Acala (2000)`.
+	pub fn para(&self, para_id: ParaId) -> String {
+		match self.paras.read().unwrap_or_else(|e| e.into_inner()).get(&para_id) {
+			Some(n) => format!("{n} ({para_id})"),
+			None => para_id.to_string(),
+		}
+	}
+}
+#[test]
+fn names_should_work() {
+	let alice = [0; 32];
+	let names = Names::new(&Naming {
+		identity: true,
+		paras: [(2000, "Acala".into())].into_iter().collect(),
+	});
+
+	assert_eq!(names.para(2000), "Acala (2000)");
+	assert_eq!(names.para(2004), "2004");
+	assert_eq!(names.account(&alice), "5C4hrfjw9DjXZTzV3MwzrrAr9P1MJhSrvWGWqi1eSuyUpnhM");
+
+	names.identities_mut().insert(alice, Some("Alice".into()));
+
+	assert_eq!(names.account(&alice), "Alice (5C4hrfjw9DjXZTzV3MwzrrAr9P1MJhSrvWGWqi1eSuyUpnhM)");
+
+	names.reset(&Naming::default());
+
+	assert_eq!(names.para(2000), "2000");
+	assert_eq!(names.account(&alice), "5C4hrfjw9DjXZTzV3MwzrrAr9P1MJhSrvWGWqi1eSuyUpnhM");
+}
+
+/// Read the display name from `Identity::IdentityOf`.
+///
+/// Newer runtimes store `(Registration, Option<Username>)` instead of `Registration`.
+fn display_of<T>(registration: &scale_value::Value<T>) -> Option<String> {
+	let info = registration.at("info").or_else(|| registration.at(0)?.at("info"))?;
+	let ValueDef::Variant(display) = &info.at("display")?.value else { return None };
+
+	if !display.name.starts_with("Raw") {
+		return None;
+	}
+
+	let ValueDef::Composite(bytes) = &display.values.values().next()?.value else { return None };
+	let bytes = bytes.values().map(|b| b.as_u128().map(|b| b as u8)).collect::<Option<Vec<_>>>()?;
+
+	String::from_utf8(bytes).ok().filter(|s| !s.is_empty())
+}
+#[test]
+fn display_of_should_work() {
+	let registration = |display| {
+		Value::named_composite([(
+			"info",
+			Value::named_composite([
+				("display", display),
+				("legal", Value::unnamed_variant("None", [])),
+			]),
+		)])
+	};
+	let raw = |s: &str| {
+		Value::unnamed_variant(
+			format!("Raw{}", s.len()),
+			[Value::unnamed_composite(s.bytes().map(|b| Value::u128(b as _)))],
+		)
+	};
+
+	assert_eq!(display_of(&registration(raw("Alice"))).as_deref(), Some("Alice"));
+	assert_eq!(
+		display_of(&Value::unnamed_composite([
+			registration(raw("Alice")),
+			Value::unnamed_variant("None", [])
+		]))
+		.as_deref(),
+		Some("Alice")
+	);
+	assert_eq!(display_of(&registration(raw(""))), None);
+	assert_eq!(display_of(&registration(Value::unnamed_variant("None", []))), None);
+}
+
+impl Hunter {
+	/// Fetch the identities of the accounts which are not cached yet.
+	///
+	/// Failures aren't cached, since they might be transient, the accounts will be fetched again
+	/// next time.
+	pub async fn resolve_identities(&self, accounts: impl IntoIterator<Item = &AccountId>) {
+		if !self.configuration.naming.identity {
+			return;
+		}
+
+		for who in accounts {
+			if self.names.identities_mut().contains_key(who) {
+				continue;
+			}
+
+			match self.identity_of(who).await {
+				Ok(i) => {
+					self.names.identities_mut().insert(*who, i);
+				},
+				Err(e) => tracing::debug!(
+					"failed to fetch the identity of account({}) due to error({e})",
+					util::ss58(who)
+				),
+			}
+		}
+	}
+
+	/// Return `None` if the account has no identity or the network has no identity pallet.
+	async fn identity_of(&self, who: &AccountId) -> Result<Option<String>> {
+		if self.node.metadata().pallet_by_name("Identity").is_none() {
+			return Ok(None);
+		}
+
+		let _timer = self.metrics.rpc_timer("identity_of");
+		let Some(registration) = self
+			.node
+			.storage()
+			.at_latest()
+			.await?
+			.fetch(&dynamic::storage("Identity", "IdentityOf", vec![Value::from_bytes(who)]))
+			.await?
+		else {
+			return Ok(None);
+		};
+
+		Ok(display_of(&registration.to_value()?))
+	}
+}
@@ -1,35 +1,149 @@
+// std
+use std::{
+	future::Future,
+	time::{Duration, Instant},
+};
 // crates.io
 use parity_scale_codec::Decode;
 use serde::Deserialize;
 use subxt::{
+	backend::legacy::LegacyRpcMethods,
 	config::polkadot::H256,
 	dynamic::{self, At, Value},
 };
 // slothunter
 use crate::hunter::*;
 
-const E_DE: &str = "failed to decode/deserialize, type need to be updated";
-const E_TYPE_CONVERSION: &str = "type conversion never fails";
+/// Read a number from the dynamic value.
+fn u128_of<T>(value: Option<&scale_value::Value<T>>, what: &str) -> Result<u128> {
+	Ok(value.and_then(scale_value::Value::as_u128).ok_or_else(|| Error::decode(what))?)
+}
+
+/// Retries of a transient query failure, after which it escalates to the supervisor.
+const QUERY_RETRIES: u32 = 3;
+/// Upper bound of the time spent on retrying a query, well below the block time, so a retried
+/// query can't make the hunter miss the blocks in the ending period.
+const QUERY_RETRY_BUDGET: Duration = Duration::from_secs(2);
 
 impl Hunter {
-	pub async fn auction_ending_period(&self) -> Result<BlockNumber> {
-		Ok(self
-			.node
-			.constants()
-			.at(&dynamic::constant("Auctions", "EndingPeriod"))?
-			.to_value()?
-			.as_u128()
-			.expect(E_TYPE_CONVERSION) as _)
+	/// Run the query, retry the transient failures in place with a backoff.
+	///
+	/// The supervisor only restarts the hunter once the retries have been exhausted.
+	async fn retry_query<T, F>(&self, name: &str, query: impl Fn() -> F) -> Result<T>
+	where
+		F: Future<Output = Result<T>>,
+	{
+		let mut backoff = util::Backoff::new(Duration::from_millis(250), Duration::from_secs(1));
+		let mut retries = 0;
+		let started_at = Instant::now();
+
+		loop {
+			let result = {
+				let _timer = self.metrics.rpc_timer(name);
+
+				query().await
+			};
+
+			match result.map_err(Error::from) {
+				Ok(r) => return Ok(r),
+				Err(e @ Error::Transient(_)) if retries < QUERY_RETRIES => {
+					let delay = backoff.next();
+
+					if started_at.elapsed() + delay > QUERY_RETRY_BUDGET {
+						return Err(e.into());
+					}
+
+					retries += 1;
+
+					tracing::warn!(
+						"query({name}) failed due to error({e}), retry({retries}/{QUERY_RETRIES}) in {delay:?}"
+					);
+
+					tokio::time::sleep(delay).await;
+				},
+				Err(e) => Err(e)?,
+			}
+		}
+	}
+
+	pub fn auction_ending_period(&self) -> Result<BlockNumber> {
+		let v =
+			self.node.constants().at(&dynamic::constant("Auctions", "EndingPeriod"))?.to_value()?;
+
+		Ok(u128_of(Some(&v), "`Auctions::EndingPeriod`")? as _)
+	}
+
+	pub fn auction_sample_length(&self) -> Result<BlockNumber> {
+		let v =
+			self.node.constants().at(&dynamic::constant("Auctions", "SampleLength"))?.to_value()?;
+
+		Ok(u128_of(Some(&v), "`Auctions::SampleLength`")? as _)
+	}
+
+	/// Return the lease period index of the given block height.
+	///
+	/// The constants are read from the metadata, no RPC is involved.
+	pub fn lease_period_index_at(&self, height: BlockNumber) -> Result<u32> {
+		let constant = |name| -> Result<BlockNumber> {
+			let v = self.node.constants().at(&dynamic::constant("Slots", name))?.to_value()?;
+
+			Ok(u128_of(Some(&v), &format!("`Slots::{name}`"))? as _)
+		};
+
+		Ok(height.saturating_sub(constant("LeaseOffset")?) / constant("LeasePeriod")?)
+	}
+
+	pub async fn para_registered_at(&self, block: &H256, para_id: ParaId) -> Result<bool> {
+		self.retry_query("para_registered_at", || async move {
+			Ok(self
+				.node
+				.storage()
+				.at(block.to_owned())
+				.fetch(&dynamic::storage("Registrar", "Paras", vec![Value::u128(para_id as _)]))
+				.await?
+				.is_some())
+		})
+		.await
+	}
+
+	pub async fn free_balance_at(&self, block: &H256, who: &AccountId) -> Result<Balance> {
+		self.retry_query("free_balance_at", || async move {
+			if let Some(a) = self
+				.node
+				.storage()
+				.at(block.to_owned())
+				.fetch(&dynamic::storage("System", "Account", vec![Value::from_bytes(who)]))
+				.await?
+			{
+				u128_of(a.to_value()?.at("data").and_then(|d| d.at("free")), "`System::Account`")
+			} else {
+				Ok(0)
+			}
+		})
+		.await
+	}
+
+	fn legacy_rpc(&self) -> LegacyRpcMethods<PolkadotConfig> {
+		LegacyRpcMethods::new(self.rpc.clone())
+	}
+
+	pub async fn best_block_number(&self) -> Result<BlockNumber> {
+		self.retry_query("best_block_number", || async move {
+			Ok(self
+				.legacy_rpc()
+				.chain_get_header(None)
+				.await?
+				.map(|h| h.number)
+				.unwrap_or_default())
+		})
+		.await
 	}
 
-	pub async fn auction_sample_length(&self) -> Result<BlockNumber> {
-		Ok(self
-			.node
-			.constants()
-			.at(&dynamic::constant("Auctions", "SampleLength"))?
-			.to_value()?
-			.as_u128()
-			.expect(E_TYPE_CONVERSION) as _)
+	pub async fn block_hash_of(&self, number: BlockNumber) -> Result<Option<H256>> {
+		self.retry_query("block_hash_of", || async move {
+			Ok(self.legacy_rpc().chain_get_block_hash(Some(number.into())).await?)
+		})
+		.await
 	}
 
 	pub async fn proxies_at(
@@ -37,124 +151,151 @@ impl Hunter {
 		block: &H256,
 		real: &AccountId,
 	) -> Result<Option<Vec<ProxyDefinition>>> {
-		self.node
-			.storage()
-			.at(block.to_owned())
-			.fetch(&dynamic::storage("Proxy", "Proxies", vec![Value::from_bytes(real)]))
-			.await?
-			.map(|p| {
-				// https://github.com/paritytech/substrate/blob/51b2f0ed6af8dd4facb18f1a489e192fd0673f7b/frame/proxy/src/lib.rs#L573
-				let (p, _) =
-					<(Vec<UnnamedWrapper<ProxyDefinition>>, Balance)>::deserialize(p.to_value()?)
-						.expect(E_DE);
-				let r: Result<Vec<ProxyDefinition>> = Ok(p.into_iter().map(|p| p.r#type).collect());
-
-				r
-			})
-			.transpose()
+		self.retry_query("proxies_at", || async move {
+			self.node
+				.storage()
+				.at(block.to_owned())
+				.fetch(&dynamic::storage("Proxy", "Proxies", vec![Value::from_bytes(real)]))
+				.await?
+				.map(|p| {
+					// https://github.com/paritytech/substrate/blob/51b2f0ed6af8dd4facb18f1a489e192fd0673f7b/frame/proxy/src/lib.rs#L573
+					let (p, _) = <(Vec<UnnamedWrapper<ProxyDefinition>>, Balance)>::deserialize(
+						p.to_value()?,
+					)
+					.map_err(|e| Error::decode(format!("`Proxy::Proxies`, {e}")))?;
+					let r: Result<Vec<ProxyDefinition>> =
+						Ok(p.into_iter().map(|p| p.r#type).collect());
+
+					r
+				})
+				.transpose()
+		})
+		.await
 	}
 
 	pub async fn auction_at(&self, block: &H256) -> Result<Option<AuctionDetail>> {
-		const E_STORAGE_TYPE: &str = "`AuctionInfo` has an invalid storage type";
-
-		if let Some(auction_info) = self
-			.node
-			.storage()
-			.at(block.to_owned())
-			.fetch(&dynamic::storage("Auctions", "AuctionInfo", <Vec<()>>::new()))
-			.await?
-		{
-			let auction_info = auction_info.to_value()?;
-			let auction_counter = self
+		self.retry_query("auction_at", || async move {
+			if let Some(auction_info) = self
 				.node
 				.storage()
 				.at(block.to_owned())
-				.fetch(&dynamic::storage("Auctions", "AuctionCounter", <Vec<()>>::new()))
+				.fetch(&dynamic::storage("Auctions", "AuctionInfo", <Vec<()>>::new()))
 				.await?
-				.expect("`Auction::AuctionCounter` must exist");
-			let auction_counter = auction_counter.to_value()?;
-
-			Ok(Some(AuctionDetail {
-				index: auction_counter.as_u128().expect(E_TYPE_CONVERSION) as _,
-				first_lease_period: auction_info
-					.at(0)
-					.expect(E_STORAGE_TYPE)
-					.as_u128()
-					.expect(E_TYPE_CONVERSION) as _,
-				ending_period_start_at: auction_info
-					.at(1)
-					.expect(E_STORAGE_TYPE)
-					.as_u128()
-					.expect(E_TYPE_CONVERSION) as _,
-			}))
-		} else {
-			Ok(None)
-		}
+			{
+				let auction_info = auction_info.to_value()?;
+				let auction_counter = self
+					.node
+					.storage()
+					.at(block.to_owned())
+					.fetch(&dynamic::storage("Auctions", "AuctionCounter", <Vec<()>>::new()))
+					.await?
+					.ok_or_else(|| Error::decode("`Auctions::AuctionCounter`, it must exist"))?;
+				let auction_counter = auction_counter.to_value()?;
+
+				Ok(Some(AuctionDetail {
+					index: u128_of(Some(&auction_counter), "`Auctions::AuctionCounter`")? as _,
+					first_lease_period: u128_of(auction_info.at(0), "`Auctions::AuctionInfo`")?
+						as _,
+					ending_period_start_at: u128_of(auction_info.at(1), "`Auctions::AuctionInfo`")?
+						as _,
+				}))
+			} else {
+				Ok(None)
+			}
+		})
+		.await
 	}
 
 	pub async fn fund_index_at(&self, block: &H256) -> Result<Option<u32>> {
-		if let Some(f) = self
-			.node
-			.storage()
-			.at(block.to_owned())
-			.fetch(&dynamic::storage(
-				"Crowdloan",
-				"Funds",
-				vec![Value::u128(self.configuration.bid.para_id as _)],
-			))
-			.await?
-		{
-			Ok(Some(
-				f.to_value()?.at("fund_index").expect(E_DE).as_u128().expect(E_TYPE_CONVERSION)
-					as _,
-			))
-		} else {
-			Ok(None)
-		}
+		self.retry_query("fund_index_at", || async move {
+			if let Some(f) = self
+				.node
+				.storage()
+				.at(block.to_owned())
+				.fetch(&dynamic::storage(
+					"Crowdloan",
+					"Funds",
+					vec![Value::u128(self.configuration.bid.para_id as _)],
+				))
+				.await?
+			{
+				Ok(Some(u128_of(f.to_value()?.at("fund_index"), "`Crowdloan::Funds`")? as _))
+			} else {
+				Ok(None)
+			}
+		})
+		.await
 	}
 
-	pub async fn bidders_at(&self, block: &H256) -> Result<Vec<Bidder>> {
-		let mut bidders = Vec::new();
-		let mut bidders_storage = self
-			.node
-			.storage()
-			.at(block.to_owned())
-			.iter(dynamic::storage("Auctions", "ReservedAmounts", <Vec<()>>::new()))
-			.await?;
-
-		while let Some(r) = bidders_storage.next().await {
-			let (k, v) = r?;
-			// twox64_concat
-			// (twox128(b"Auctions") + twox128(b"ReservedAmounts") + twox64(key)).len() = 40
-			// key = k.0[40..]
-			let (who, para_id) = <(AccountId, ParaId)>::decode(&mut &k[40..]).expect(E_DE);
-			let existing_deposit = self
+	pub async fn bidders_at(&self, block: &H256, index: &BidIndex) -> Result<Vec<Bidder>> {
+		self.retry_query("bidders_at", || async move {
+			let mut bidders = Vec::new();
+			let mut bidders_storage = self
 				.node
 				.storage()
 				.at(block.to_owned())
-				.fetch(&dynamic::storage("Slots", "Leases", vec![Value::u128(para_id as _)]))
-				.await?
-				.and_then(|l: dynamic::DecodedValueThunk| {
-					SLeases::decode(&mut &*l.into_encoded())
-						.expect(E_DE)
-						.into_iter()
-						.filter_map(|l| l.and_then(|(w, a)| if who == w { Some(a) } else { None }))
-						.max()
-				})
-				.unwrap_or_default();
-			let last_accepted_bid =
-				self.last_accepted_bid_of(&array_bytes::bytes2hex("0x", who), para_id).await?;
-
-			bidders.push(Bidder {
-				who,
-				para_id,
-				reserved: v.to_value()?.as_u128().expect(E_TYPE_CONVERSION),
-				existing_deposit,
-				last_accepted_bid,
-			});
-		}
+				.iter(dynamic::storage("Auctions", "ReservedAmounts", <Vec<()>>::new()))
+				.await?;
+
+			while let Some(r) = bidders_storage.next().await {
+				let (k, v) = r?;
+				// twox64_concat
+				// (twox128(b"Auctions") + twox128(b"ReservedAmounts") + twox64(key)).len() = 40
+				// key = k.0[40..]
+				let (who, para_id) = <(AccountId, ParaId)>::decode(&mut &k[40..]).map_err(|e| {
+					Error::decode(format!("`Auctions::ReservedAmounts`'s key, {e}"))
+				})?;
+				let existing_deposit = self
+					.node
+					.storage()
+					.at(block.to_owned())
+					.fetch(&dynamic::storage("Slots", "Leases", vec![Value::u128(para_id as _)]))
+					.await?
+					.map(|l: dynamic::DecodedValueThunk| {
+						SLeases::decode(&mut &*l.into_encoded())
+							.map_err(|e| Error::decode(format!("`Slots::Leases`, {e}")))
+					})
+					.transpose()?
+					.and_then(|l| {
+						l.into_iter()
+							.filter_map(|l| {
+								l.and_then(|(w, a)| if who == w { Some(a) } else { None })
+							})
+							.max()
+					})
+					.unwrap_or_default();
+				// A partial index might miss the latest bid, prefer GraphQL until it's complete.
+				let last_accepted_bid = match index.last_accepted_bid_of(&who, para_id) {
+					Some(b) if index.complete => Some(b),
+					b if self.configuration.graphql_endpoint.is_some() => {
+						// GraphQL is only an accelerator, do not abort if it fails.
+						let queried =
+							self.last_accepted_bid_of(&who, para_id).await.unwrap_or_else(|e| {
+								tracing::warn!(
+									"failed to query the last accepted bid due to error({e})"
+								);
 
-		Ok(bidders)
+								None
+							});
+
+						// GraphQL might lag behind the live blocks, take the later one.
+						queried.into_iter().chain(b).max_by_key(|b| b.at)
+					},
+					b => b,
+				};
+
+				bidders.push(Bidder {
+					who,
+					para_id,
+					reserved: u128_of(Some(&v.to_value()?), "`Auctions::ReservedAmounts`")?,
+					existing_deposit,
+					last_accepted_bid,
+				});
+			}
+
+			Ok(bidders)
+		})
+		.await
 	}
 
 	pub async fn winning_at(
@@ -163,15 +304,67 @@ impl Hunter {
 		now: BlockNumber,
 		ending_period_start_at: BlockNumber,
 	) -> Result<Option<Winning>> {
-		let winning_offset =
-			util::winning_offset_of(now, ending_period_start_at, self.auction_sample_length);
-
-		Ok(self
-			.node
-			.storage()
-			.at(block.to_owned())
-			.fetch(&dynamic::storage("Auctions", "Winning", vec![Value::u128(winning_offset as _)]))
-			.await?
-			.map(|w| Winning::of(SWinning::decode(&mut &*w.into_encoded()).expect(E_DE))))
+		self.retry_query("winning_at", || async move {
+			let winning_offset =
+				util::winning_offset_of(now, ending_period_start_at, self.auction_sample_length);
+
+			self.node
+				.storage()
+				.at(block.to_owned())
+				.fetch(&dynamic::storage(
+					"Auctions",
+					"Winning",
+					vec![Value::u128(winning_offset as _)],
+				))
+				.await?
+				.map(|w| {
+					Ok(Winning::of(
+						SWinning::decode(&mut &*w.into_encoded())
+							.map_err(|e| Error::decode(format!("`Auctions::Winning`, {e}")))?,
+					))
+				})
+				.transpose()
+		})
+		.await
+	}
+
+	pub async fn auction_events_of(&self, block: &Block) -> Result<Vec<Phased<AuctionEvent>>> {
+		let (events, failures) = self
+			.retry_query("auction_events_of", || async move {
+				let mut events = Vec::new();
+				let mut failures = Vec::new();
+
+				for e in block.events().await?.iter() {
+					let e = e?;
+
+					match AuctionEvent::decode(&e) {
+						Ok(Some(a)) => events.push(Phased::new(e.phase(), a)),
+						Ok(None) => (),
+						// Skip the event instead of the whole block, so the others are still
+						// handled.
+						Err(err) => failures.push(Error::decode(format!(
+							"`{}::{}` at block({}), {err}",
+							e.pallet_name(),
+							e.variant_name(),
+							block.number()
+						))),
+					}
+				}
+
+				Ok((events, failures))
+			})
+			.await?;
+
+		// Only once the query has succeeded, the retries would repeat them otherwise.
+		for error in failures {
+			tracing::error!("{error}");
+
+			self.notify(&Context::new(
+				NotificationEvent::DecodeFailed { error: error.to_string() },
+				error.to_string(),
+			));
+		}
+
+		Ok(events)
 	}
 }
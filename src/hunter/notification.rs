@@ -1,18 +1,41 @@
-// crates.io
-use lettre::{
-	message::Mailbox, transport::smtp::authentication::Credentials, Message, SmtpTransport,
-	Transport,
+mod delivery;
+pub use delivery::*;
+
+mod event;
+pub use event::*;
+
+mod notifier;
+pub use notifier::*;
+
+mod template;
+pub use template::*;
+
+mod throttle;
+pub use throttle::*;
+
+// std
+use std::{
+	collections::HashMap,
+	sync::MutexGuard,
+	time::{Instant, SystemTime, UNIX_EPOCH},
 };
-use serde::Serialize;
+// crates.io
+use lettre::message::Mailbox;
 // slothunter
 use crate::hunter::*;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 pub struct Mail {
 	pub sender: Sender,
 	pub receivers: Vec<Mailbox>,
+	pub subscription: Subscription,
+	pub throttle: Throttle,
+}
+impl Mail {
+	/// Key which identifies the mail channel.
+	pub const KEY: &'static str = "mail";
 }
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Sender {
 	pub username: Mailbox,
 	pub password: String,
@@ -20,59 +43,135 @@ pub struct Sender {
 }
 
 impl Hunter {
-	pub fn notify_mail<S>(&self, object: &S, addition: &str)
-	where
-		S: Serialize,
-	{
-		if let Some(m) = &self.configuration.notification.mail {
-			let notification = serde_json::to_string(&serde_json::json!({
-				"object": object,
-				"addition": addition,
-			}))
-			.expect("json must be valid");
-
-			for to in &m.receivers {
-				let mail = Message::builder()
-					.from(m.sender.username.clone())
-					.to(to.to_owned())
-					.subject("Slothunter")
-					.body(notification.clone())
-					.expect("message must be valid");
-				let smtp_transport = SmtpTransport::relay(&m.sender.smtp)
-					.expect("smtp must be valid")
-					.credentials(Credentials::new(
-						m.sender.username.email.to_string(),
-						m.sender.password.clone(),
-					))
-					.build();
-
-				let _ = smtp_transport.send(&mail);
+	/// Enqueue the notification to the channels which subscribe to the event.
+	///
+	/// The notification might be held by the channel's [`Throttle`] for a later digest.
+	pub fn notify(&self, context: &Context) {
+		let notification = &self.configuration.notification;
+		let (subject, body) = notification.templates.render(context);
+		let now = Instant::now();
+		let admit = |channel: &str, throttle: &Throttle| {
+			self.throttles.admit(channel, throttle, context.severity, &body, now)
+		};
+
+		if let Some(i) = context.incident.as_ref().filter(|i| !i.resolved) {
+			self.open_incidents().insert(i.condition, i.auction);
+		}
+
+		if let Some(m) = &notification.mail {
+			if m.subscription.accepts(context.event, context.severity)
+				&& admit(Mail::KEY, &m.throttle)
+			{
+				self.push_mail(m, &subject, &body);
+			}
+		}
+
+		let object = serde_json::to_value(context).expect("json must be valid");
+		let transaction_id = transaction_id();
+
+		for c in &notification.notifiers {
+			// Never hold the incidents of the paging channels, a held resolution would leave the
+			// incident open.
+			if c.accepts(context) && (c.notifier.is_paging() || admit(&c.key(), &c.throttle)) {
+				self.push_webhook(c, &object, &subject, &body, &transaction_id);
 			}
 		}
 	}
 
-	pub async fn notify_webhook<S>(&self, object: &S, addition: &str)
-	where
-		S: Serialize,
-	{
-		let json = serde_json::json!({
-			"object": object,
-			"addition": addition,
-		});
+	/// Send the held notifications of the channels whose digests are due.
+	///
+	/// The digests bypass the subscriptions, since the held notifications have been accepted.
+	pub(super) fn notify_digests(&self, state: &State) {
+		let notification = &self.configuration.notification;
+		let now = Instant::now();
+		let digest = |channel: &str, throttle: &Throttle| {
+			let (held, dropped) =
+				self.throttles.take_digest(channel, throttle, state.block_height, now)?;
+			let count = held.len() + dropped;
+			let mut message = format!("{count} held notification(s)");
 
-		for u in &self.configuration.notification.webhooks {
-			if u.starts_with("https://hooks.slack.com/services/") {
-				let _ = self
-					.http
-					.post(u)
-					.json(&serde_json::json!({
-						"text": serde_json::to_string(&json).unwrap(),
-					}))
-					.send()
-					.await;
-			} else {
-				let _ = self.http.post(u).json(&json).send().await;
+			if dropped != 0 {
+				message.push_str(&format!(", the oldest {dropped} have been dropped"));
+			}
+
+			held.iter().for_each(|m| {
+				message.push_str("\n\n");
+				message.push_str(m);
+			});
+
+			Some(self.context(state, NotificationEvent::Digest { count, dropped }, message))
+		};
+
+		if let Some(m) = &notification.mail {
+			if let Some(c) = digest(Mail::KEY, &m.throttle) {
+				let (subject, body) = notification.templates.render(&c);
+
+				self.push_mail(m, &subject, &body);
+			}
+		}
+
+		for c in notification.notifiers.iter().filter(|c| !c.notifier.is_paging()) {
+			if let Some(context) = digest(&c.key(), &c.throttle) {
+				let (subject, body) = notification.templates.render(&context);
+				let object = serde_json::to_value(&context).expect("json must be valid");
+
+				self.push_webhook(c, &object, &subject, &body, &transaction_id());
 			}
 		}
 	}
+
+	fn open_incidents(&self) -> MutexGuard<'_, HashMap<Condition, Option<u32>>> {
+		// The incidents are always consistent, ignore the poisoning.
+		self.incidents.lock().unwrap_or_else(|e| e.into_inner())
+	}
+
+	/// Resolve the incident of the condition if it's open.
+	pub(super) fn resolve(&self, condition: Condition) {
+		let Some(auction) = self.open_incidents().remove(&condition) else { return };
+		let n = format!("incident({condition}) has been resolved");
+
+		tracing::info!("{n}");
+
+		self.notify(&Context::new(NotificationEvent::Resolved { condition, auction }, n));
+	}
+
+	fn push_mail(&self, mail: &Mail, subject: &str, body: &str) {
+		for to in &mail.receivers {
+			self.deliveries.push(Delivery::Mail {
+				sender: mail.sender.clone(),
+				to: to.to_owned(),
+				subject: subject.into(),
+				body: body.into(),
+				policy: DeliveryPolicy::default(),
+			});
+		}
+	}
+
+	fn push_webhook(
+		&self,
+		channel: &Channel,
+		object: &serde_json::Value,
+		subject: &str,
+		body: &str,
+		transaction_id: &str,
+	) {
+		self.deliveries.push(Delivery::Webhook {
+			notifier: channel.notifier.clone(),
+			object: object.to_owned(),
+			subject: subject.into(),
+			body: body.into(),
+			transaction_id: transaction_id.into(),
+			policy: channel.policy.clone(),
+			secret: channel.secret.clone(),
+		});
+	}
+}
+
+/// Unique ID of a notification, which is also used as the idempotency key.
+fn transaction_id() -> String {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map(|d| d.as_nanos())
+		.unwrap_or_default()
+		.to_string()
 }
@@ -0,0 +1,639 @@
+// std
+use std::{
+	collections::{hash_map::DefaultHasher, HashMap, HashSet},
+	hash::{Hash, Hasher},
+	sync::{Arc, Mutex, MutexGuard},
+	time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+// crates.io
+use hmac::{Hmac, Mac};
+use lettre::{
+	message::Mailbox,
+	transport::smtp::{authentication::Credentials, PoolConfig},
+	AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use reqwest::Client;
+use serde::Deserialize;
+use sha2::Sha256;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+// slothunter
+use crate::hunter::*;
+
+type Mailer = AsyncSmtpTransport<Tokio1Executor>;
+
+/// Unix timestamp in seconds of the delivery attempt.
+pub const HEADER_TIMESTAMP: &str = "X-Slothunter-Timestamp";
+/// Unique key of the notification, which stays the same across the retries.
+pub const HEADER_IDEMPOTENCY_KEY: &str = "X-Slothunter-Idempotency-Key";
+/// HMAC-SHA256 signature of the payload, see [`sign`].
+pub const HEADER_SIGNATURE: &str = "X-Slothunter-Signature";
+
+/// Upper bound of the delay between two retries.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Delivery settings of a channel.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct DeliveryPolicy {
+	/// Request timeout in seconds.
+	pub timeout: u64,
+	/// Number of retries after the first failed attempt.
+	pub retries: u32,
+	/// Delay before the first retry in milliseconds, it is doubled on every following retry.
+	pub retry_delay: u64,
+}
+impl DeliveryPolicy {
+	/// Return the delay before the given retry, starting from 1.
+	pub fn retry_delay(&self, retry: u32) -> Duration {
+		Duration::from_millis(self.retry_delay)
+			.saturating_mul(2_u32.saturating_pow(retry.saturating_sub(1)))
+			.min(MAX_RETRY_DELAY)
+	}
+}
+impl Default for DeliveryPolicy {
+	fn default() -> Self {
+		Self { timeout: 10, retries: 3, retry_delay: 1_000 }
+	}
+}
+#[test]
+fn delivery_policy_retry_delay_should_work() {
+	let p = DeliveryPolicy::default();
+
+	assert_eq!(p.retry_delay(1), Duration::from_secs(1));
+	assert_eq!(p.retry_delay(3), Duration::from_secs(4));
+	assert_eq!(p.retry_delay(100), MAX_RETRY_DELAY);
+}
+
+/// Sign the payload as `sha256=<hex(HMAC-SHA256(secret, "{timestamp}.{body}"))>`.
+///
+/// The timestamp is signed together with the body, so the receiver can reject the replays.
+pub fn sign(secret: &str, timestamp: &str, body: &[u8]) -> String {
+	let mut mac =
+		Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+
+	mac.update(timestamp.as_bytes());
+	mac.update(b".");
+	mac.update(body);
+
+	format!("sha256={}", array_bytes::bytes2hex("", mac.finalize().into_bytes()))
+}
+#[test]
+fn sign_should_work() {
+	assert_eq!(
+		sign("secret", "1700000000", br#"{"a":1}"#),
+		"sha256=49f24e537407743fa4a0242bb63b94b9a47ee99cbbe071ccd8a22550ae411686"
+	);
+}
+
+/// Notification to be delivered by the background queue.
+#[derive(Debug)]
+pub enum Delivery {
+	Mail {
+		sender: Sender,
+		to: Mailbox,
+		subject: String,
+		body: String,
+		policy: DeliveryPolicy,
+	},
+	Webhook {
+		notifier: Notifier,
+		object: serde_json::Value,
+		subject: String,
+		body: String,
+		transaction_id: String,
+		policy: DeliveryPolicy,
+		/// Secret to sign the payload with.
+		secret: Option<String>,
+	},
+}
+impl Delivery {
+	/// Return the channel name, which is also the metrics label.
+	pub fn channel(&self) -> &'static str {
+		match self {
+			Self::Mail { .. } => "mail",
+			Self::Webhook { notifier, .. } => notifier.name(),
+		}
+	}
+
+	pub fn target(&self) -> String {
+		match self {
+			Self::Mail { to, .. } => to.to_string(),
+			Self::Webhook { notifier, .. } => notifier.target().into(),
+		}
+	}
+
+	pub fn policy(&self) -> &DeliveryPolicy {
+		match self {
+			Self::Mail { policy, .. } | Self::Webhook { policy, .. } => policy,
+		}
+	}
+
+	/// Identical deliveries share the same key.
+	fn key(&self) -> u64 {
+		let mut hasher = DefaultHasher::new();
+
+		self.channel().hash(&mut hasher);
+		self.target().hash(&mut hasher);
+
+		match self {
+			Self::Mail { subject, body, .. } | Self::Webhook { subject, body, .. } => {
+				subject.hash(&mut hasher);
+				body.hash(&mut hasher);
+			},
+		}
+
+		hasher.finish()
+	}
+}
+
+/// Background queue which delivers the notifications without blocking the hunter.
+///
+/// Mails share one pooled SMTP connection. Failed deliveries are retried according to their
+/// [`DeliveryPolicy`], a delivery identical to a pending one is dropped.
+///
+/// Deliveries to the same channel and target are serial, so they arrive in order, e.g. an
+/// incident's resolve never overtakes its trigger.
+#[derive(Debug)]
+pub struct DeliveryQueue {
+	sender: UnboundedSender<Delivery>,
+	/// Keys of the queued and the retrying deliveries.
+	pending: Arc<Mutex<HashSet<u64>>>,
+}
+impl DeliveryQueue {
+	/// Spawn the queue's worker, which exits once the queue is dropped.
+	pub fn spawn(http: Client, metrics: Arc<Metrics>) -> Self {
+		let (sender, receiver) = mpsc::unbounded_channel();
+		let pending = Arc::<Mutex<HashSet<_>>>::default();
+		let deliverer = Deliverer { http, metrics, pending: pending.clone() };
+
+		tokio::spawn(deliverer.run(receiver));
+
+		Self { sender, pending }
+	}
+
+	/// Enqueue the delivery, it will be dropped if an identical one is still pending.
+	pub fn push(&self, delivery: Delivery) {
+		let key = delivery.key();
+
+		if !lock(&self.pending).insert(key) {
+			tracing::debug!(
+				"skipped the duplicate notification to {}({})",
+				delivery.channel(),
+				delivery.target()
+			);
+
+			return;
+		}
+		if self.sender.send(delivery).is_err() {
+			lock(&self.pending).remove(&key);
+
+			tracing::error!("notification delivery queue has been closed");
+		}
+	}
+
+	/// Return the number of the queued and the retrying deliveries.
+	pub fn pending(&self) -> usize {
+		lock(&self.pending).len()
+	}
+
+	/// Wait until all the pending deliveries finish or the timeout elapses.
+	pub async fn flush(&self, timeout: Duration) {
+		let deadline = Instant::now() + timeout;
+
+		while self.pending() != 0 && Instant::now() < deadline {
+			tokio::time::sleep(Duration::from_millis(100)).await;
+		}
+	}
+}
+
+fn lock(pending: &Mutex<HashSet<u64>>) -> MutexGuard<'_, HashSet<u64>> {
+	// The set is always consistent, ignore the poisoning.
+	pending.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[derive(Clone)]
+struct Deliverer {
+	http: Client,
+	metrics: Arc<Metrics>,
+	pending: Arc<Mutex<HashSet<u64>>>,
+}
+impl Deliverer {
+	async fn run(self, mut receiver: UnboundedReceiver<Delivery>) {
+		// The transport is rebuilt only if the sender has been changed by a reloading.
+		let mut mailer = None::<(Sender, Mailer)>;
+		// A worker for each channel and target, the different targets don't block each other.
+		let mut lanes = HashMap::<_, UnboundedSender<(Delivery, Option<Mailer>)>>::new();
+
+		while let Some(delivery) = receiver.recv().await {
+			let transport = match &delivery {
+				Delivery::Mail { sender, .. } => match &mailer {
+					Some((s, m)) if s == sender => Some(m.clone()),
+					_ => match build_mailer(sender) {
+						Ok(m) => {
+							mailer = Some((sender.to_owned(), m.clone()));
+
+							Some(m)
+						},
+						Err(e) => {
+							self.fail(&delivery, 0, e);
+
+							continue;
+						},
+					},
+				},
+				Delivery::Webhook { .. } => None,
+			};
+
+			let lane = lanes.entry((delivery.channel(), delivery.target())).or_insert_with(|| {
+				let (sender, mut receiver) = mpsc::unbounded_channel();
+				let deliverer = self.clone();
+
+				tokio::spawn(async move {
+					while let Some((delivery, transport)) = receiver.recv().await {
+						deliverer.clone().deliver_with_retries(delivery, transport).await;
+					}
+				});
+
+				sender
+			});
+
+			if let Err(mpsc::error::SendError((delivery, _))) = lane.send((delivery, transport)) {
+				lock(&self.pending).remove(&delivery.key());
+
+				tracing::error!(
+					"notification delivery lane of {}({}) has been closed",
+					delivery.channel(),
+					delivery.target()
+				);
+			}
+		}
+	}
+
+	async fn deliver_with_retries(self, delivery: Delivery, mailer: Option<Mailer>) {
+		let attempts = delivery.policy().retries.saturating_add(1);
+
+		for attempt in 1..=attempts {
+			match self.deliver(&delivery, mailer.as_ref()).await {
+				Ok(()) => {
+					self.metrics
+						.notification_deliveries
+						.with_label_values(&[delivery.channel()])
+						.inc();
+					lock(&self.pending).remove(&delivery.key());
+
+					return;
+				},
+				Err(e) if attempt < attempts => {
+					let delay = delivery.policy().retry_delay(attempt);
+
+					tracing::debug!(
+						"failed to send the notification to {}({}) due to error({e}), retrying in {delay:?}",
+						delivery.channel(),
+						delivery.target()
+					);
+
+					tokio::time::sleep(delay).await;
+				},
+				Err(e) => self.fail(&delivery, attempt, e),
+			}
+		}
+	}
+
+	fn fail(&self, delivery: &Delivery, attempts: u32, e: anyhow::Error) {
+		tracing::warn!(
+			"failed to send the notification to {}({}) after {attempts} attempt(s) due to error({e})",
+			delivery.channel(),
+			delivery.target()
+		);
+
+		self.metrics.notification_failures.with_label_values(&[delivery.channel()]).inc();
+		lock(&self.pending).remove(&delivery.key());
+	}
+
+	async fn deliver(&self, delivery: &Delivery, mailer: Option<&Mailer>) -> Result<()> {
+		match delivery {
+			Delivery::Mail { sender, to, subject, body, .. } => {
+				let mail = Message::builder()
+					.from(sender.username.clone())
+					.to(to.to_owned())
+					.subject(subject)
+					.body(body.to_owned())?;
+
+				mailer.expect("mailer must be built for the mail").send(mail).await?;
+			},
+			Delivery::Webhook {
+				notifier,
+				object,
+				subject,
+				body,
+				transaction_id,
+				policy,
+				secret,
+			} => {
+				let (method, url, body) =
+					notifier.request(object, subject, body, transaction_id)?;
+				let body = serde_json::to_vec(&body)?;
+				let timestamp = SystemTime::now()
+					.duration_since(UNIX_EPOCH)
+					.map(|d| d.as_secs())
+					.unwrap_or_default()
+					.to_string();
+				let mut request = self
+					.http
+					.request(method, url)
+					.timeout(Duration::from_secs(policy.timeout))
+					.header(HEADER_IDEMPOTENCY_KEY, transaction_id);
+
+				if let Some(s) = secret {
+					request = request.header(HEADER_SIGNATURE, sign(s, &timestamp, &body));
+				}
+				if let Notifier::Matrix { access_token, .. } = notifier {
+					request = request.bearer_auth(access_token);
+				}
+
+				request
+					.header(HEADER_TIMESTAMP, timestamp)
+					.body(body)
+					.send()
+					.await?
+					.error_for_status()?;
+			},
+		}
+
+		Ok(())
+	}
+}
+
+/// Build the SMTP transport with one pooled connection.
+///
+/// `smtp` is either a relay server, which is connected through TLS, or a connection URL, such as
+/// `smtp://127.0.0.1:2525`.
+fn build_mailer(sender: &Sender) -> Result<Mailer> {
+	let builder = if sender.smtp.contains("://") {
+		Mailer::from_url(&sender.smtp)?
+	} else {
+		Mailer::relay(&sender.smtp)?
+	};
+
+	Ok(builder
+		.credentials(Credentials::new(sender.username.email.to_string(), sender.password.clone()))
+		.pool_config(PoolConfig::new().max_size(1))
+		.build())
+}
+
+#[tokio::test]
+async fn delivery_queue_should_work() {
+	// std
+	use std::sync::atomic::{AtomicBool, Ordering};
+	// crates.io
+	use tokio::{
+		io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+		net::TcpListener,
+	};
+
+	// A minimal SMTP server which rejects the first transaction temporarily.
+	let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+	let port = listener.local_addr().unwrap().port();
+	let mails = Arc::<Mutex<Vec<String>>>::default();
+	let rejected = Arc::new(AtomicBool::new(false));
+
+	{
+		let mails = mails.clone();
+
+		tokio::spawn(async move {
+			loop {
+				let (stream, _) = listener.accept().await.unwrap();
+				let mails = mails.clone();
+				let rejected = rejected.clone();
+
+				tokio::spawn(async move {
+					let (r, mut w) = stream.into_split();
+					let mut r = BufReader::new(r);
+					let mut line = String::new();
+
+					w.write_all(b"220 localhost\r\n").await.unwrap();
+
+					while r.read_line(&mut line).await.unwrap() != 0 {
+						let command = line.to_uppercase();
+						let reply: &[u8] = if command.starts_with("EHLO") {
+							b"250-localhost\r\n250 AUTH PLAIN LOGIN\r\n"
+						} else if command.starts_with("AUTH") {
+							b"235 ok\r\n"
+						} else if command.starts_with("MAIL") {
+							if rejected.swap(true, Ordering::SeqCst) {
+								b"250 ok\r\n"
+							} else {
+								b"451 try again later\r\n"
+							}
+						} else if command.starts_with("DATA") {
+							w.write_all(b"354 go ahead\r\n").await.unwrap();
+
+							let mut mail = String::new();
+
+							while !mail.ends_with("\r\n.\r\n") {
+								r.read_line(&mut mail).await.unwrap();
+							}
+
+							mails.lock().unwrap().push(mail);
+
+							b"250 ok\r\n"
+						} else if command.starts_with("QUIT") {
+							b"221 bye\r\n"
+						} else {
+							b"250 ok\r\n"
+						};
+
+						w.write_all(reply).await.unwrap();
+						line.clear();
+					}
+				});
+			}
+		});
+	}
+
+	let metrics = Arc::<Metrics>::default();
+	let queue = DeliveryQueue::spawn(util::http_json_client(), metrics.clone());
+	let policy = DeliveryPolicy { timeout: 1, retries: 1, retry_delay: 10 };
+	let mail = || Delivery::Mail {
+		sender: Sender {
+			username: "slothunter@example.com".parse().unwrap(),
+			password: "password".into(),
+			smtp: format!("smtp://127.0.0.1:{port}"),
+		},
+		to: "alice@example.com".parse().unwrap(),
+		subject: "Slothunter winners".into(),
+		body: "winners".into(),
+		policy: policy.clone(),
+	};
+
+	queue.push(mail());
+	queue.push(mail());
+
+	assert_eq!(queue.pending(), 1);
+
+	// Nothing listens on the discard port.
+	queue.push(Delivery::Webhook {
+		notifier: Notifier::Json { uri: "http://127.0.0.1:9/".into() },
+		object: serde_json::Value::Null,
+		subject: "Slothunter winners".into(),
+		body: "winners".into(),
+		transaction_id: "0".into(),
+		policy,
+		secret: None,
+	});
+	queue.flush(Duration::from_secs(10)).await;
+
+	assert_eq!(queue.pending(), 0);
+	assert_eq!(mails.lock().unwrap().len(), 1);
+	assert!(mails.lock().unwrap()[0].contains("Subject: Slothunter winners"));
+	assert_eq!(metrics.notification_deliveries.with_label_values(&["mail"]).get(), 1);
+	assert_eq!(metrics.notification_failures.with_label_values(&["mail"]).get(), 0);
+	assert_eq!(metrics.notification_failures.with_label_values(&["json"]).get(), 1);
+}
+
+/// Start a minimal HTTP server which fails the first `failures` requests.
+///
+/// Return its port and the received raw requests.
+#[cfg(test)]
+async fn mock_http_server(failures: usize) -> (u16, Arc<Mutex<Vec<String>>>) {
+	// crates.io
+	use tokio::{
+		io::{AsyncReadExt, AsyncWriteExt},
+		net::TcpListener,
+	};
+
+	let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+	let port = listener.local_addr().unwrap().port();
+	let requests = Arc::<Mutex<Vec<String>>>::default();
+	let requests_ = requests.clone();
+
+	tokio::spawn(async move {
+		loop {
+			let (mut stream, _) = listener.accept().await.unwrap();
+			let mut request = Vec::new();
+			let mut buffer = [0; 1024];
+
+			loop {
+				let n = stream.read(&mut buffer).await.unwrap();
+
+				request.extend_from_slice(&buffer[..n]);
+
+				let r = String::from_utf8_lossy(&request);
+				let Some((head, body)) = r.split_once("\r\n\r\n") else { continue };
+				let length = head
+					.lines()
+					.find_map(|l| l.strip_prefix("content-length: "))
+					.map_or(0, |l| l.parse().unwrap());
+
+				if n == 0 || body.len() >= length {
+					break;
+				}
+			}
+
+			let reply: &[u8] = {
+				let mut requests = requests_.lock().unwrap();
+
+				requests.push(String::from_utf8(request).unwrap());
+
+				if requests.len() <= failures {
+					b"HTTP/1.1 500 Internal Server Error\r\ncontent-length: 0\r\n\r\n"
+				} else {
+					b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n"
+				}
+			};
+
+			stream.write_all(reply).await.unwrap();
+		}
+	});
+
+	(port, requests)
+}
+
+#[tokio::test]
+async fn webhook_delivery_should_be_signed() {
+	let (port, requests) = mock_http_server(1).await;
+	let metrics = Arc::<Metrics>::default();
+	let queue = DeliveryQueue::spawn(util::http_json_client(), metrics.clone());
+
+	queue.push(Delivery::Webhook {
+		notifier: Notifier::Json { uri: format!("http://127.0.0.1:{port}/webhook") },
+		object: serde_json::json!({ "index": 1 }),
+		subject: "Slothunter winners".into(),
+		body: "winners".into(),
+		transaction_id: "1700000000000000000".into(),
+		policy: DeliveryPolicy { timeout: 1, retries: 1, retry_delay: 10 },
+		secret: Some("secret".into()),
+	});
+	queue.flush(Duration::from_secs(10)).await;
+
+	let requests = requests.lock().unwrap();
+
+	assert_eq!(requests.len(), 2);
+	assert_eq!(metrics.notification_deliveries.with_label_values(&["json"]).get(), 1);
+
+	for r in requests.iter() {
+		let header = |name: &str| {
+			r.lines()
+				.find_map(|l| l.strip_prefix(&format!("{}: ", name.to_lowercase())))
+				.unwrap()
+				.to_owned()
+		};
+		let body = r.split("\r\n\r\n").nth(1).unwrap();
+
+		assert_eq!(header(HEADER_IDEMPOTENCY_KEY), "1700000000000000000");
+		assert_eq!(
+			header(HEADER_SIGNATURE),
+			sign("secret", &header(HEADER_TIMESTAMP), body.as_bytes())
+		);
+	}
+}
+
+#[tokio::test]
+async fn paging_should_trigger_and_resolve() {
+	// The trigger is retried once, the resolve must still arrive after it.
+	let (port, requests) = mock_http_server(1).await;
+	let metrics = Arc::<Metrics>::default();
+	let queue = DeliveryQueue::spawn(util::http_json_client(), metrics.clone());
+	let auction = AuctionDetail { index: 1, first_lease_period: 10, ending_period_start_at: 100 };
+	let page = |event| {
+		let context = Context::new(event, "").auction(&auction, 0);
+
+		Delivery::Webhook {
+			notifier: Notifier::Pagerduty {
+				routing_key: "x".into(),
+				events_uri: format!("http://127.0.0.1:{port}/v2/enqueue"),
+				source: "slothunter".into(),
+			},
+			object: serde_json::to_value(&context).unwrap(),
+			subject: context.event.to_string(),
+			body: context.message,
+			transaction_id: "0".into(),
+			policy: DeliveryPolicy { timeout: 1, retries: 1, retry_delay: 100 },
+			secret: None,
+		}
+	};
+
+	queue.push(page(NotificationEvent::Unaffordable { amount: 2, upper_limit: 1 }));
+	queue.push(page(NotificationEvent::Resolved {
+		condition: Condition::Unaffordable,
+		auction: None,
+	}));
+	queue.flush(Duration::from_secs(10)).await;
+
+	let requests = requests
+		.lock()
+		.unwrap()
+		.iter()
+		.map(|r| {
+			serde_json::from_str::<serde_json::Value>(r.split("\r\n\r\n").nth(1).unwrap()).unwrap()
+		})
+		.collect::<Vec<_>>();
+
+	assert_eq!(requests.len(), 3);
+	assert_eq!(requests[0]["event_action"], "trigger");
+	assert_eq!(requests[1]["event_action"], "trigger");
+	assert_eq!(requests[2]["event_action"], "resolve");
+	assert_eq!(requests[0]["dedup_key"], "slothunter/unaffordable/auction-1");
+	assert_eq!(requests[0]["dedup_key"], requests[2]["dedup_key"]);
+	assert_eq!(metrics.notification_deliveries.with_label_values(&["pagerduty"]).get(), 2);
+}
@@ -0,0 +1,352 @@
+// std
+use std::fmt::{Display, Formatter, Result as FmtResult};
+// crates.io
+use serde::{Deserialize, Serialize};
+// slothunter
+use crate::hunter::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Severity {
+	/// Routine updates, such as the changes of the winners.
+	Debug,
+	Info,
+	/// Something might need attention.
+	Warning,
+	/// Something must be handled manually.
+	Critical,
+}
+impl Display for Severity {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		match self {
+			Self::Debug => write!(f, "debug"),
+			Self::Info => write!(f, "info"),
+			Self::Warning => write!(f, "warning"),
+			Self::Critical => write!(f, "critical"),
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EventKind {
+	AuctionStarted,
+	AuctionClosed,
+	Outbid,
+	Leased,
+	Winners,
+	WinningStatusChanged,
+	BidSubmitted,
+	BidFailed,
+	Unaffordable,
+	TenderSkipped,
+	ApprovalRequested,
+	LowBalance,
+	ConnectionLost,
+	DecodeFailed,
+	Reloaded,
+	Control,
+	Stopped,
+	Restarting,
+	Escalated,
+	Digest,
+	Resolved,
+}
+impl EventKind {
+	pub const ALL: [Self; 21] = [
+		Self::AuctionStarted,
+		Self::AuctionClosed,
+		Self::Outbid,
+		Self::Leased,
+		Self::Winners,
+		Self::WinningStatusChanged,
+		Self::BidSubmitted,
+		Self::BidFailed,
+		Self::Unaffordable,
+		Self::TenderSkipped,
+		Self::ApprovalRequested,
+		Self::LowBalance,
+		Self::ConnectionLost,
+		Self::DecodeFailed,
+		Self::Reloaded,
+		Self::Control,
+		Self::Stopped,
+		Self::Restarting,
+		Self::Escalated,
+		Self::Digest,
+		Self::Resolved,
+	];
+}
+impl Display for EventKind {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		let s = match self {
+			Self::AuctionStarted => "auction-started",
+			Self::AuctionClosed => "auction-closed",
+			Self::Outbid => "outbid",
+			Self::Leased => "leased",
+			Self::Winners => "winners",
+			Self::WinningStatusChanged => "winning-status-changed",
+			Self::BidSubmitted => "bid-submitted",
+			Self::BidFailed => "bid-failed",
+			Self::Unaffordable => "unaffordable",
+			Self::TenderSkipped => "tender-skipped",
+			Self::ApprovalRequested => "approval-requested",
+			Self::LowBalance => "low-balance",
+			Self::ConnectionLost => "connection-lost",
+			Self::DecodeFailed => "decode-failed",
+			Self::Reloaded => "reloaded",
+			Self::Control => "control",
+			Self::Stopped => "stopped",
+			Self::Restarting => "restarting",
+			Self::Escalated => "escalated",
+			Self::Digest => "digest",
+			Self::Resolved => "resolved",
+		};
+
+		write!(f, "{s}")
+	}
+}
+#[test]
+fn event_kind_display_should_match_serde() {
+	for k in EventKind::ALL {
+		assert_eq!(serde_json::to_value(k).unwrap(), k.to_string());
+	}
+}
+
+/// Typed notification event, the payload is accessible as `data` in the templates.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum NotificationEvent {
+	AuctionStarted(AuctionDetail),
+	AuctionClosed(AuctionDetail),
+	/// Another bidder has bid for the leases which intersect ours.
+	Outbid(EBidAccepted),
+	/// Our parachain has leased.
+	Leased(ELeased),
+	Winners {
+		winners: Vec<Winner>,
+		threshold: Balance,
+	},
+	/// We have become a winner or have been outbid.
+	WinningStatusChanged {
+		winning: bool,
+	},
+	BidSubmitted {
+		amount: Balance,
+	},
+	BidFailed {
+		amount: Balance,
+		error: String,
+	},
+	/// The bid to win exceeds the upper limit.
+	Unaffordable {
+		amount: Balance,
+		upper_limit: Balance,
+	},
+	/// Tendering was skipped because of the pause or the watch-only mode.
+	TenderSkipped {
+		amount: Balance,
+	},
+	/// A bid has been proposed to the bot and is waiting for the approval.
+	ApprovalRequested {
+		id: u64,
+		amount: Balance,
+	},
+	/// The real account's free balance is lower than the upper limit.
+	LowBalance {
+		free: Balance,
+		upper_limit: Balance,
+	},
+	ConnectionLost,
+	/// An event has been skipped since it couldn't be decoded, the runtime might have been
+	/// upgraded.
+	DecodeFailed {
+		error: String,
+	},
+	Reloaded,
+	Control(Command),
+	Stopped {
+		error: String,
+	},
+	Restarting {
+		error: String,
+		failures: usize,
+	},
+	/// Too many failures within the supervision window.
+	Escalated {
+		error: String,
+		failures: usize,
+	},
+	/// Notifications held by the rate limit or the digest mode.
+	Digest {
+		count: usize,
+		/// Number of the oldest held notifications which have been dropped.
+		dropped: usize,
+	},
+	/// The condition of an incident has cleared.
+	Resolved {
+		condition: Condition,
+		auction: Option<u32>,
+	},
+}
+impl NotificationEvent {
+	pub fn kind(&self) -> EventKind {
+		match self {
+			Self::AuctionStarted(_) => EventKind::AuctionStarted,
+			Self::AuctionClosed(_) => EventKind::AuctionClosed,
+			Self::Outbid(_) => EventKind::Outbid,
+			Self::Leased(_) => EventKind::Leased,
+			Self::Winners { .. } => EventKind::Winners,
+			Self::WinningStatusChanged { .. } => EventKind::WinningStatusChanged,
+			Self::BidSubmitted { .. } => EventKind::BidSubmitted,
+			Self::BidFailed { .. } => EventKind::BidFailed,
+			Self::Unaffordable { .. } => EventKind::Unaffordable,
+			Self::TenderSkipped { .. } => EventKind::TenderSkipped,
+			Self::ApprovalRequested { .. } => EventKind::ApprovalRequested,
+			Self::LowBalance { .. } => EventKind::LowBalance,
+			Self::ConnectionLost => EventKind::ConnectionLost,
+			Self::DecodeFailed { .. } => EventKind::DecodeFailed,
+			Self::Reloaded => EventKind::Reloaded,
+			Self::Control(_) => EventKind::Control,
+			Self::Stopped { .. } => EventKind::Stopped,
+			Self::Restarting { .. } => EventKind::Restarting,
+			Self::Escalated { .. } => EventKind::Escalated,
+			Self::Digest { .. } => EventKind::Digest,
+			Self::Resolved { .. } => EventKind::Resolved,
+		}
+	}
+
+	pub fn severity(&self) -> Severity {
+		match self {
+			Self::Winners { .. } => Severity::Debug,
+			Self::AuctionStarted(_)
+			| Self::AuctionClosed(_)
+			| Self::Leased(_)
+			| Self::WinningStatusChanged { winning: true }
+			| Self::BidSubmitted { .. }
+			| Self::Reloaded
+			| Self::Control(_)
+			| Self::Digest { .. }
+			| Self::Resolved { .. } => Severity::Info,
+			Self::Outbid(_)
+			| Self::WinningStatusChanged { winning: false }
+			| Self::TenderSkipped { .. }
+			| Self::ApprovalRequested { .. }
+			| Self::LowBalance { .. }
+			| Self::DecodeFailed { .. }
+			| Self::Restarting { .. } => Severity::Warning,
+			Self::BidFailed { .. }
+			| Self::Unaffordable { .. }
+			| Self::ConnectionLost
+			| Self::Stopped { .. }
+			| Self::Escalated { .. } => Severity::Critical,
+		}
+	}
+
+	/// Return the incident which the event raises or resolves.
+	pub fn incident(&self) -> Option<Incident> {
+		let (condition, auction, resolved) = match self {
+			Self::BidFailed { .. } => (Condition::BidFailed, None, false),
+			Self::Unaffordable { .. } => (Condition::Unaffordable, None, false),
+			Self::ConnectionLost => (Condition::ConnectionLost, None, false),
+			Self::Resolved { condition, auction } => (*condition, *auction, true),
+			_ => return None,
+		};
+
+		Some(Incident { condition, auction, resolved })
+	}
+}
+
+/// Condition which requires someone to act, it pages the paging notifiers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Condition {
+	BidFailed,
+	Unaffordable,
+	ConnectionLost,
+}
+impl Display for Condition {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		match self {
+			Self::BidFailed => write!(f, "bid-failed"),
+			Self::Unaffordable => write!(f, "unaffordable"),
+			Self::ConnectionLost => write!(f, "connection-lost"),
+		}
+	}
+}
+
+/// An incident is identified by its condition and the auction in which it was raised.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Incident {
+	pub condition: Condition,
+	pub auction: Option<u32>,
+	pub resolved: bool,
+}
+impl Incident {
+	/// Return the deduplication key, which is shared by the trigger and the resolve.
+	pub fn dedup_key(&self, source: &str) -> String {
+		match self.auction {
+			Some(a) => format!("{source}/{}/auction-{a}", self.condition),
+			None => format!("{source}/{}", self.condition),
+		}
+	}
+}
+#[test]
+fn incident_should_work() {
+	let raised = NotificationEvent::BidFailed { amount: 1, error: "x".into() }.incident().unwrap();
+
+	assert_eq!(raised.dedup_key("slothunter"), "slothunter/bid-failed");
+	assert!(!raised.resolved);
+
+	let resolved =
+		NotificationEvent::Resolved { condition: Condition::BidFailed, auction: Some(1) }
+			.incident()
+			.unwrap();
+
+	assert_eq!(resolved.dedup_key("slothunter"), "slothunter/bid-failed/auction-1");
+	assert!(resolved.resolved);
+	assert_eq!(NotificationEvent::Reloaded.incident(), None);
+}
+
+/// Events which a channel subscribes to.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Subscription {
+	/// Subscribed event kinds, all of them if empty.
+	pub events: Vec<EventKind>,
+	/// Minimum severity, the default value depends on the channel.
+	pub min_severity: Option<Severity>,
+}
+impl Subscription {
+	pub fn with_default_severity(mut self, severity: Severity) -> Self {
+		self.min_severity.get_or_insert(severity);
+
+		self
+	}
+
+	pub fn accepts(&self, kind: EventKind, severity: Severity) -> bool {
+		(self.events.is_empty() || self.events.contains(&kind))
+			&& severity >= self.min_severity.unwrap_or(Severity::Debug)
+	}
+}
+#[test]
+fn subscription_should_work() {
+	let s = Subscription::default();
+
+	assert!(s.accepts(EventKind::Winners, Severity::Debug));
+
+	let s = s.with_default_severity(Severity::Info);
+
+	assert!(!s.accepts(EventKind::Winners, Severity::Debug));
+	assert!(s.accepts(EventKind::AuctionStarted, Severity::Info));
+
+	let s = Subscription {
+		events: vec![EventKind::BidFailed, EventKind::Outbid],
+		min_severity: Some(Severity::Critical),
+	}
+	.with_default_severity(Severity::Info);
+
+	assert!(s.accepts(EventKind::BidFailed, Severity::Critical));
+	assert!(!s.accepts(EventKind::Outbid, Severity::Warning));
+	assert!(!s.accepts(EventKind::Unaffordable, Severity::Critical));
+}
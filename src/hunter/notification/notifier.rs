@@ -0,0 +1,412 @@
+// crates.io
+use reqwest::{Method, Url};
+use serde::Deserialize;
+use serde_json::{json, Value};
+// slothunter
+use crate::hunter::*;
+
+/// Discord rejects the messages which are longer than this.
+const DISCORD_CONTENT_LIMIT: usize = 2000;
+/// Telegram rejects the messages which are longer than this.
+const TELEGRAM_TEXT_LIMIT: usize = 4096;
+/// PagerDuty rejects the summaries which are longer than this.
+const PAGERDUTY_SUMMARY_LIMIT: usize = 1024;
+
+/// A notifier with its subscription and delivery settings.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct Channel {
+	#[serde(flatten)]
+	pub notifier: Notifier,
+	#[serde(flatten)]
+	pub subscription: Subscription,
+	#[serde(flatten)]
+	pub policy: DeliveryPolicy,
+	#[serde(flatten)]
+	pub throttle: Throttle,
+	/// Secret to sign the payloads with, see [`sign`].
+	pub secret: Option<String>,
+}
+impl Channel {
+	/// Return whether the channel accepts the notification.
+	///
+	/// The paging notifiers only accept the incidents.
+	pub fn accepts(&self, context: &Context) -> bool {
+		self.subscription.accepts(context.event, context.severity)
+			&& (!self.notifier.is_paging() || context.incident.is_some())
+	}
+
+	/// Return the key which identifies the channel.
+	pub fn key(&self) -> String {
+		format!("{}({})", self.notifier.name(), self.notifier.target())
+	}
+
+	/// Check the configuration, return the problem if any.
+	pub fn check(&self) -> StdResult<(), String> {
+		self.notifier.check()?;
+		self.throttle.check()?;
+
+		if self.policy.timeout == 0 {
+			return Err("`timeout` must be greater than 0".into());
+		}
+		if self.secret.as_ref().is_some_and(|s| s.is_empty()) {
+			return Err("`secret` must not be empty".into());
+		}
+
+		Ok(())
+	}
+}
+
+/// A typed notification backend.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Notifier {
+	/// Slack incoming webhook.
+	Slack { webhook: String },
+	/// Discord channel webhook.
+	Discord { webhook: String },
+	/// Telegram bot, the bot must be a member of the chat.
+	#[serde(rename_all = "kebab-case")]
+	Telegram { bot_token: String, chat_id: String },
+	/// Matrix room, the user of the access token must be a member of the room.
+	#[serde(rename_all = "kebab-case")]
+	Matrix { homeserver: String, room_id: String, access_token: String },
+	/// Generic JSON webhook, which receives the event data and the rendered body as
+	/// `{"object", "addition"}`.
+	Json { uri: String },
+	/// PagerDuty Events API v2 or a compatible paging service.
+	///
+	/// It triggers an incident when a [`Condition`] is raised and resolves it once cleared.
+	#[serde(rename_all = "kebab-case")]
+	Pagerduty {
+		routing_key: String,
+		#[serde(default = "default_events_uri")]
+		events_uri: String,
+		/// Source of the incidents, which is also the prefix of the deduplication keys.
+		#[serde(default = "default_source")]
+		source: String,
+	},
+}
+fn default_events_uri() -> String {
+	"https://events.pagerduty.com/v2/enqueue".into()
+}
+fn default_source() -> String {
+	"slothunter".into()
+}
+impl Notifier {
+	/// Convert a bare webhook URI of the legacy `notification.webhooks`.
+	pub fn of_webhook(uri: String) -> Self {
+		if uri.starts_with("https://hooks.slack.com/services/") {
+			Self::Slack { webhook: uri }
+		} else {
+			Self::Json { uri }
+		}
+	}
+
+	pub fn name(&self) -> &'static str {
+		match self {
+			Self::Slack { .. } => "slack",
+			Self::Discord { .. } => "discord",
+			Self::Telegram { .. } => "telegram",
+			Self::Matrix { .. } => "matrix",
+			Self::Json { .. } => "json",
+			Self::Pagerduty { .. } => "pagerduty",
+		}
+	}
+
+	/// Return the destination without the credentials.
+	pub fn target(&self) -> &str {
+		match self {
+			Self::Slack { webhook } | Self::Discord { webhook } => webhook,
+			Self::Telegram { chat_id, .. } => chat_id,
+			Self::Matrix { room_id, .. } => room_id,
+			Self::Json { uri } => uri,
+			Self::Pagerduty { source, .. } => source,
+		}
+	}
+
+	/// Check the configuration, return the problem if any.
+	pub fn check(&self) -> StdResult<(), String> {
+		match self {
+			Self::Slack { webhook: uri } | Self::Discord { webhook: uri } | Self::Json { uri } =>
+				if !util::check_http_uri(uri) {
+					return Err(format!("invalid uri({uri})"));
+				},
+			Self::Telegram { bot_token, chat_id } =>
+				if bot_token.is_empty() || chat_id.is_empty() {
+					return Err("`bot-token` and `chat-id` are required".into());
+				},
+			Self::Matrix { homeserver, room_id, access_token } => {
+				if !util::check_http_uri(homeserver) {
+					return Err(format!("invalid homeserver({homeserver})"));
+				}
+				if room_id.is_empty() || access_token.is_empty() {
+					return Err("`room-id` and `access-token` are required".into());
+				}
+			},
+			Self::Pagerduty { routing_key, events_uri, source } => {
+				if !util::check_http_uri(events_uri) {
+					return Err(format!("invalid events-uri({events_uri})"));
+				}
+				if routing_key.is_empty() || source.is_empty() {
+					return Err("`routing-key` and `source` must not be empty".into());
+				}
+			},
+		}
+
+		Ok(())
+	}
+
+	pub fn is_paging(&self) -> bool {
+		matches!(self, Self::Pagerduty { .. })
+	}
+
+	/// Build the request of the rendered message.
+	///
+	/// `transaction_id` is only used by Matrix to deduplicate the retries.
+	pub fn request(
+		&self,
+		object: &Value,
+		subject: &str,
+		body: &str,
+		transaction_id: &str,
+	) -> Result<(Method, Url, Value)> {
+		let titled = format!("{subject}\n{body}");
+
+		Ok(match self {
+			Self::Slack { webhook } => (Method::POST, webhook.parse()?, json!({ "text": titled })),
+			Self::Discord { webhook } => (
+				Method::POST,
+				webhook.parse()?,
+				json!({ "content": truncate(&titled, DISCORD_CONTENT_LIMIT) }),
+			),
+			Self::Telegram { bot_token, chat_id } => (
+				Method::POST,
+				format!("https://api.telegram.org/bot{bot_token}/sendMessage").parse()?,
+				json!({
+					"chat_id": chat_id,
+					"text": truncate(&titled, TELEGRAM_TEXT_LIMIT),
+					"disable_web_page_preview": true,
+				}),
+			),
+			Self::Matrix { homeserver, room_id, .. } => {
+				let mut url = homeserver.parse::<Url>()?;
+
+				url.path_segments_mut()
+					.map_err(|_| anyhow::anyhow!("invalid homeserver({homeserver})"))?
+					.pop_if_empty()
+					.extend([
+						"_matrix",
+						"client",
+						"v3",
+						"rooms",
+						room_id,
+						"send",
+						"m.room.message",
+						transaction_id,
+					]);
+
+				(Method::PUT, url, json!({ "msgtype": "m.notice", "body": titled }))
+			},
+			Self::Json { uri } =>
+				(Method::POST, uri.parse()?, json!({ "object": object, "addition": body })),
+			Self::Pagerduty { routing_key, events_uri, source } => {
+				let incident =
+					serde_json::from_value::<Option<Incident>>(object["incident"].clone())?
+						.ok_or_else(|| {
+							anyhow::anyhow!("paging notifier only accepts the incidents")
+						})?;
+				let dedup_key = incident.dedup_key(source);
+				let body = if incident.resolved {
+					json!({
+						"routing_key": routing_key,
+						"event_action": "resolve",
+						"dedup_key": dedup_key,
+					})
+				} else {
+					let severity = object["severity"]
+						.as_str()
+						.filter(|s| matches!(*s, "critical" | "warning"))
+						.unwrap_or("info");
+
+					json!({
+						"routing_key": routing_key,
+						"event_action": "trigger",
+						"dedup_key": dedup_key,
+						"payload": {
+							"summary": truncate(&titled, PAGERDUTY_SUMMARY_LIMIT),
+							"source": source,
+							"severity": severity,
+							"custom_details": object,
+						},
+					})
+				};
+
+				(Method::POST, events_uri.parse()?, body)
+			},
+		})
+	}
+}
+#[test]
+fn notifier_request_should_work() {
+	let object = json!({ "index": 1 });
+	let request = |n: Notifier| {
+		n.request(&object, "Slothunter", "auction has just been started", "1").unwrap()
+	};
+
+	assert_eq!(
+		request(Notifier::of_webhook("https://hooks.slack.com/services/x".into())),
+		(
+			Method::POST,
+			"https://hooks.slack.com/services/x".parse().unwrap(),
+			json!({ "text": "Slothunter\nauction has just been started" })
+		)
+	);
+	assert_eq!(
+		request(Notifier::Discord { webhook: "https://discord.com/api/webhooks/x".into() }).2,
+		json!({ "content": "Slothunter\nauction has just been started" })
+	);
+	assert_eq!(
+		request(Notifier::Telegram { bot_token: "0:x".into(), chat_id: "-1".into() }),
+		(
+			Method::POST,
+			"https://api.telegram.org/bot0:x/sendMessage".parse().unwrap(),
+			json!({
+				"chat_id": "-1",
+				"text": "Slothunter\nauction has just been started",
+				"disable_web_page_preview": true,
+			})
+		)
+	);
+
+	let long = "x".repeat(5_000);
+	let text_of = |n: Notifier, key: &str| {
+		n.request(&object, "Slothunter", &long, "1").unwrap().2[key].as_str().unwrap().to_owned()
+	};
+	let discord = text_of(Notifier::Discord { webhook: "https://discord.com/x".into() }, "content");
+	let telegram =
+		text_of(Notifier::Telegram { bot_token: "0:x".into(), chat_id: "-1".into() }, "text");
+
+	assert_eq!(discord.chars().count(), DISCORD_CONTENT_LIMIT);
+	assert!(discord.ends_with('…'));
+	assert_eq!(telegram.chars().count(), TELEGRAM_TEXT_LIMIT);
+	assert!(telegram.ends_with('…'));
+
+	let (method, url, _) = request(Notifier::Matrix {
+		homeserver: "https://matrix.org/".into(),
+		room_id: "!x:matrix.org".into(),
+		access_token: "x".into(),
+	});
+
+	assert_eq!(method, Method::PUT);
+	assert_eq!(
+		url.as_str(),
+		"https://matrix.org/_matrix/client/v3/rooms/!x:matrix.org/send/m.room.message/1"
+	);
+	assert_eq!(
+		request(Notifier::of_webhook("https://example.com/webhook".into())).2,
+		json!({ "object": { "index": 1 }, "addition": "auction has just been started" })
+	);
+}
+#[test]
+fn pagerduty_request_should_work() {
+	let notifier = toml::from_str::<Notifier>(
+		r#"
+type = "pagerduty"
+routing-key = "x"
+"#,
+	)
+	.unwrap();
+	let request = |event| {
+		let context = Context::new(event, "").auction(
+			&AuctionDetail { index: 1, first_lease_period: 10, ending_period_start_at: 100 },
+			0,
+		);
+
+		notifier
+			.request(&serde_json::to_value(context).unwrap(), "Slothunter", "bid failed", "1")
+			.unwrap()
+	};
+
+	let (method, url, body) =
+		request(NotificationEvent::BidFailed { amount: 1, error: "x".into() });
+
+	assert_eq!((method, url.as_str()), (Method::POST, "https://events.pagerduty.com/v2/enqueue"));
+	assert_eq!(body["event_action"], "trigger");
+	assert_eq!(body["dedup_key"], "slothunter/bid-failed/auction-1");
+	assert_eq!(body["payload"]["severity"], "critical");
+	assert_eq!(body["payload"]["summary"], "Slothunter\nbid failed");
+	assert_eq!(
+		request(NotificationEvent::Resolved { condition: Condition::BidFailed, auction: Some(1) })
+			.2,
+		json!({
+			"routing_key": "x",
+			"event_action": "resolve",
+			"dedup_key": "slothunter/bid-failed/auction-1",
+		})
+	);
+	assert!(notifier.request(&json!({ "incident": null }), "", "", "1").is_err());
+}
+#[test]
+fn notifier_should_deserialize() {
+	#[derive(Deserialize)]
+	struct T {
+		notifiers: Vec<Channel>,
+	}
+
+	assert_eq!(
+		toml::from_str::<T>(
+			r#"
+[[notifiers]]
+type = "telegram"
+bot-token = "0:x"
+chat-id = "-1"
+secret = "x"
+
+[[notifiers]]
+type = "matrix"
+events = ["bid-failed", "unaffordable"]
+min-severity = "critical"
+homeserver = "https://matrix.org"
+room-id = "!x:matrix.org"
+access-token = "x"
+timeout = 5
+retries = 0
+rate-limit = 10
+digest = 100
+"#
+		)
+		.unwrap()
+		.notifiers,
+		[
+			Channel {
+				notifier: Notifier::Telegram { bot_token: "0:x".into(), chat_id: "-1".into() },
+				subscription: Subscription::default(),
+				policy: DeliveryPolicy::default(),
+				throttle: Throttle::default(),
+				secret: Some("x".into())
+			},
+			Channel {
+				notifier: Notifier::Matrix {
+					homeserver: "https://matrix.org".into(),
+					room_id: "!x:matrix.org".into(),
+					access_token: "x".into()
+				},
+				subscription: Subscription {
+					events: vec![EventKind::BidFailed, EventKind::Unaffordable],
+					min_severity: Some(Severity::Critical)
+				},
+				policy: DeliveryPolicy { timeout: 5, retries: 0, ..Default::default() },
+				throttle: Throttle { rate_limit: Some(10), digest: Some(100) },
+				secret: None
+			}
+		]
+	);
+}
+
+fn truncate(s: &str, limit: usize) -> String {
+	if s.chars().count() <= limit {
+		s.into()
+	} else {
+		s.chars().take(limit - 1).chain(['…']).collect()
+	}
+}
@@ -0,0 +1,257 @@
+// std
+use std::collections::HashMap;
+// crates.io
+use handlebars::Handlebars;
+use serde::{Deserialize, Serialize};
+use subxt::config::polkadot::H256;
+// slothunter
+use crate::hunter::*;
+
+/// Template of an event, in the Handlebars syntax.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct Template {
+	/// Mail subject and chat message title.
+	pub subject: String,
+	/// Mail body and chat message body.
+	pub body: String,
+}
+
+/// Compiled templates, with their sources to tell whether they have changed.
+#[derive(Debug)]
+pub struct Templates(Handlebars<'static>, HashMap<String, Template>);
+impl PartialEq for Templates {
+	fn eq(&self, other: &Self) -> bool {
+		self.1 == other.1
+	}
+}
+impl Templates {
+	pub fn new(templates: &HashMap<String, Template>) -> Result<Self> {
+		let mut registry = Handlebars::new();
+
+		// The messages are plain text, not HTML.
+		registry.register_escape_fn(handlebars::no_escape);
+		registry.register_template_string("default.subject", "Slothunter {{event}}")?;
+		registry.register_template_string("default.body", "{{message}}")?;
+
+		for (event, t) in templates {
+			if event != "default" && EventKind::ALL.iter().all(|k| &k.to_string() != event) {
+				Err(Error::Configuration(format!("unknown notification event({event})")))?;
+			}
+
+			registry.register_template_string(&format!("{event}.subject"), &t.subject)?;
+			registry.register_template_string(&format!("{event}.body"), &t.body)?;
+		}
+
+		Ok(Self(registry, templates.clone()))
+	}
+
+	/// Render the subject and the body of the event.
+	///
+	/// Fall back to the default templates if the event's ones fail.
+	pub fn render(&self, context: &Context) -> (String, String) {
+		let render = |part| {
+			let name = format!("{}.{part}", context.event);
+
+			if self.0.has_template(&name) {
+				match self.0.render(&name, context) {
+					Ok(s) => return s,
+					Err(e) =>
+						tracing::warn!("failed to render the template({name}) due to error({e})"),
+				}
+			}
+
+			self.0.render(&format!("default.{part}"), context).unwrap_or_else(|e| {
+				tracing::warn!("failed to render the template(default.{part}) due to error({e})");
+
+				context.message.clone()
+			})
+		};
+
+		(render("subject"), render("body").trim_end().into())
+	}
+}
+#[test]
+fn templates_should_work() {
+	let templates = Templates::new(
+		&[(
+			"winners".into(),
+			Template {
+				subject: "auction(#{{auction.index}}) at block(#{{block.height}})".into(),
+				body: "{{#each winners}}{{bidder}} {{parachain}} {{leases}} {{amount}}\n{{/each}}threshold {{threshold}}, remain {{auction.remaining_time}}\n".into(),
+			},
+		)]
+		.into_iter()
+		.collect(),
+	)
+	.unwrap();
+	let auction = AuctionDetail { index: 1, first_lease_period: 10, ending_period_start_at: 100 };
+	let context =
+		Context::new(NotificationEvent::Winners { winners: Vec::new(), threshold: 8 }, "| a | b |")
+			.block(20, H256::zero())
+			.auction(&auction, 600)
+			.winners(vec![WinnerContext {
+				bidder: "Alice".into(),
+				parachain: "Acala (2000)".into(),
+				leases: "#10-#17".into(),
+				amount: "KSM(1)".into(),
+			}])
+			.threshold("KSM(8)".into());
+
+	assert_eq!(
+		templates.render(&context),
+		(
+			"auction(#1) at block(#20)".into(),
+			"Alice Acala (2000) #10-#17 KSM(1)\nthreshold KSM(8), remain 0d:1h:0m:0s".into()
+		)
+	);
+	assert_eq!(
+		templates
+			.render(&Context::new(NotificationEvent::Reloaded, "configuration has been reloaded")),
+		("Slothunter reloaded".into(), "configuration has been reloaded".into())
+	);
+	assert!(Templates::new(
+		&[("unknown".into(), Template { subject: "".into(), body: "".into() })]
+			.into_iter()
+			.collect()
+	)
+	.is_err());
+	assert!(Templates::new(
+		&[("winners".into(), Template { subject: "{{#each}}".into(), body: "".into() })]
+			.into_iter()
+			.collect()
+	)
+	.is_err());
+}
+
+#[test]
+fn shipped_templates_should_work() {
+	let c =
+		toml::from_str::<ConfigurationToml>(include_str!("../../../configuration-template.toml"))
+			.unwrap();
+	let templates = Templates::new(&c.notification.templates).unwrap();
+	let auction = AuctionDetail { index: 1, first_lease_period: 10, ending_period_start_at: 100 };
+	let context =
+		Context::new(NotificationEvent::Winners { winners: Vec::new(), threshold: 8 }, "")
+			.block(20, H256::zero())
+			.auction(&auction, 10)
+			.winners(vec![WinnerContext {
+				bidder: "Alice".into(),
+				parachain: "Acala (2000)".into(),
+				leases: "#10-#17".into(),
+				amount: "KSM(1)".into(),
+			}])
+			.threshold("KSM(8)".into());
+
+	assert_eq!(
+		templates.render(&context),
+		(
+			"Slothunter auction(#1) winner(s) at block(#20)".into(),
+			"- Alice Acala (2000) won lease(s)[#10-#17] with KSM(1)\nthreshold KSM(8)\nauction(#1) remains 0d:0h:1m:0s".into()
+		)
+	);
+	assert_eq!(
+		templates
+			.render(&Context::new(NotificationEvent::Reloaded, "configuration has been reloaded")),
+		("Slothunter reloaded".into(), "configuration has been reloaded".into())
+	);
+}
+
+/// Data of an event, which is accessible in the templates.
+#[derive(Debug, Serialize)]
+pub struct Context {
+	pub event: EventKind,
+	pub severity: Severity,
+	/// The default human-readable message.
+	pub message: String,
+	pub block: Option<BlockContext>,
+	pub auction: Option<AuctionContext>,
+	pub winners: Vec<WinnerContext>,
+	/// Our bid.
+	pub bid: Option<String>,
+	/// Minimum total value to win.
+	pub threshold: Option<String>,
+	/// Payload of the event.
+	pub data: serde_json::Value,
+	/// Incident which the event raises or resolves.
+	pub incident: Option<Incident>,
+}
+impl Context {
+	pub fn new(event: NotificationEvent, message: impl Into<String>) -> Self {
+		Self {
+			event: event.kind(),
+			severity: event.severity(),
+			message: message.into(),
+			block: None,
+			auction: None,
+			winners: Vec::new(),
+			bid: None,
+			threshold: None,
+			data: serde_json::to_value(&event).expect("json must be valid"),
+			incident: event.incident(),
+		}
+	}
+
+	pub fn block(mut self, height: BlockNumber, hash: H256) -> Self {
+		self.block = Some(BlockContext { height, hash });
+
+		self
+	}
+
+	pub fn auction(mut self, auction: &AuctionDetail, remaining_blocks: BlockNumber) -> Self {
+		let (first, last) = util::range_of(auction.first_lease_period);
+
+		if let Some(i) = &mut self.incident {
+			i.auction.get_or_insert(auction.index);
+		}
+
+		self.auction = Some(AuctionContext {
+			index: auction.index,
+			first_lease_period: first,
+			last_lease_period: last,
+			ending_period_start_at: auction.ending_period_start_at,
+			remaining_blocks,
+			remaining_time: util::blocks2time(remaining_blocks),
+		});
+
+		self
+	}
+
+	pub fn winners(mut self, winners: Vec<WinnerContext>) -> Self {
+		self.winners = winners;
+
+		self
+	}
+
+	pub fn bid(mut self, bid: String) -> Self {
+		self.bid = Some(bid);
+
+		self
+	}
+
+	pub fn threshold(mut self, threshold: String) -> Self {
+		self.threshold = Some(threshold);
+
+		self
+	}
+}
+#[derive(Debug, Serialize)]
+pub struct BlockContext {
+	pub height: BlockNumber,
+	pub hash: H256,
+}
+#[derive(Debug, Serialize)]
+pub struct AuctionContext {
+	pub index: u32,
+	pub first_lease_period: u32,
+	pub last_lease_period: u32,
+	pub ending_period_start_at: BlockNumber,
+	pub remaining_blocks: BlockNumber,
+	pub remaining_time: String,
+}
+#[derive(Debug, Serialize)]
+pub struct WinnerContext {
+	pub bidder: String,
+	pub parachain: String,
+	pub leases: String,
+	pub amount: String,
+}
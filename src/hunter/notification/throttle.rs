@@ -0,0 +1,170 @@
+// std
+use std::{
+	collections::{HashMap, VecDeque},
+	mem,
+	sync::{Mutex, MutexGuard},
+	time::{Duration, Instant},
+};
+// crates.io
+use serde::Deserialize;
+// slothunter
+use crate::hunter::*;
+
+/// Window of the rate limit.
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+/// Maximum number of the held messages per channel, the older ones will be dropped.
+const MAX_HELD: usize = 50;
+
+/// Rate limit and digest settings of a channel.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Throttle {
+	/// Maximum number of notifications per minute.
+	///
+	/// The notifications over the limit are held and sent as a digest once the channel has
+	/// capacity again. The critical ones are never held.
+	pub rate_limit: Option<u32>,
+	/// Send the held notifications as a digest every N blocks.
+	///
+	/// The notifications below the warning severity are always held in this mode.
+	pub digest: Option<BlockNumber>,
+}
+impl Throttle {
+	/// Check the configuration, return the problem if any.
+	pub fn check(&self) -> StdResult<(), String> {
+		if self.rate_limit == Some(0) {
+			return Err("`rate-limit` must be greater than 0".into());
+		}
+		if self.digest == Some(0) {
+			return Err("`digest` must be greater than 0".into());
+		}
+
+		Ok(())
+	}
+}
+
+#[derive(Debug, Default)]
+struct ChannelState {
+	/// Times of the recent deliveries within the rate limit window.
+	sent: VecDeque<Instant>,
+	held: VecDeque<String>,
+	/// Number of the held messages which have been dropped because of [`MAX_HELD`].
+	dropped: usize,
+	/// Block of the last digest, or of the first check if none has been sent.
+	digested_at: Option<BlockNumber>,
+}
+impl ChannelState {
+	fn has_capacity(&mut self, throttle: &Throttle, now: Instant) -> bool {
+		while self.sent.front().is_some_and(|t| now.duration_since(*t) >= RATE_LIMIT_WINDOW) {
+			self.sent.pop_front();
+		}
+
+		throttle.rate_limit.is_none_or(|l| self.sent.len() < l as usize)
+	}
+
+	fn hold(&mut self, message: &str) {
+		if self.held.len() == MAX_HELD {
+			self.held.pop_front();
+			self.dropped += 1;
+		}
+
+		self.held.push_back(message.into());
+	}
+}
+
+/// Per-channel states of the [`Throttle`]s, the channels are identified by their keys.
+#[derive(Debug, Default)]
+pub struct Throttles(Mutex<HashMap<String, ChannelState>>);
+impl Throttles {
+	fn states(&self) -> MutexGuard<'_, HashMap<String, ChannelState>> {
+		// The states are always consistent, ignore the poisoning.
+		self.0.lock().unwrap_or_else(|e| e.into_inner())
+	}
+
+	/// Return whether to send the notification now, otherwise it's held for the next digest.
+	pub fn admit(
+		&self,
+		channel: &str,
+		throttle: &Throttle,
+		severity: Severity,
+		message: &str,
+		now: Instant,
+	) -> bool {
+		let mut states = self.states();
+		let state = states.entry(channel.into()).or_default();
+		let admitted = if throttle.digest.is_some() && severity < Severity::Warning {
+			false
+		} else {
+			severity == Severity::Critical || state.has_capacity(throttle, now)
+		};
+
+		if admitted {
+			state.sent.push_back(now);
+		} else {
+			state.hold(message);
+		}
+
+		admitted
+	}
+
+	/// Take the held messages if the channel's digest is due.
+	///
+	/// Return the messages and the number of the dropped ones.
+	pub fn take_digest(
+		&self,
+		channel: &str,
+		throttle: &Throttle,
+		block_height: BlockNumber,
+		now: Instant,
+	) -> Option<(Vec<String>, usize)> {
+		let mut states = self.states();
+		let state = states.entry(channel.into()).or_default();
+		let digested_at = *state.digested_at.get_or_insert(block_height);
+
+		if state.held.is_empty() || !state.has_capacity(throttle, now) {
+			return None;
+		}
+		if let Some(n) = throttle.digest {
+			if block_height < digested_at + n {
+				return None;
+			}
+		}
+
+		state.sent.push_back(now);
+		state.digested_at = Some(block_height);
+
+		Some((state.held.drain(..).collect(), mem::take(&mut state.dropped)))
+	}
+}
+#[test]
+fn throttles_should_work() {
+	let throttles = Throttles::default();
+	let now = Instant::now();
+	let limited = Throttle { rate_limit: Some(2), digest: None };
+
+	assert!(throttles.admit("a", &limited, Severity::Info, "1", now));
+	assert!(throttles.admit("a", &limited, Severity::Info, "2", now));
+	assert!(!throttles.admit("a", &limited, Severity::Warning, "3", now));
+	assert!(throttles.admit("a", &limited, Severity::Critical, "4", now));
+	assert_eq!(throttles.take_digest("a", &limited, 1, now), None);
+	assert_eq!(
+		throttles.take_digest("a", &limited, 1, now + RATE_LIMIT_WINDOW),
+		Some((vec!["3".into()], 0))
+	);
+
+	let digest = Throttle { rate_limit: None, digest: Some(10) };
+
+	assert_eq!(throttles.take_digest("b", &digest, 1, now), None);
+
+	(0..MAX_HELD + 1).for_each(|i| {
+		assert!(!throttles.admit("b", &digest, Severity::Debug, &i.to_string(), now));
+	});
+
+	assert!(throttles.admit("b", &digest, Severity::Warning, "warning", now));
+	assert_eq!(throttles.take_digest("b", &digest, 10, now), None);
+
+	let (held, dropped) = throttles.take_digest("b", &digest, 11, now).unwrap();
+
+	assert_eq!((held.len(), held[0].as_str(), dropped), (MAX_HELD, "1", 1));
+	assert_eq!(throttles.take_digest("b", &digest, 21, now), None);
+}
@@ -0,0 +1,160 @@
+// std
+use std::{
+	collections::VecDeque,
+	time::{Duration, Instant},
+};
+// slothunter
+use crate::hunter::*;
+
+/// Track the failures of the hunter and decide when to escalate.
+#[derive(Debug)]
+pub struct Supervisor {
+	max_failures: usize,
+	window: Duration,
+	failures: VecDeque<Instant>,
+	backoff: util::Backoff,
+	escalated: bool,
+}
+impl Supervisor {
+	pub fn new(supervision: &Supervision) -> Self {
+		Self {
+			max_failures: supervision.max_failures as _,
+			window: Duration::from_secs(supervision.window),
+			failures: VecDeque::new(),
+			backoff: util::Backoff::default(),
+			escalated: false,
+		}
+	}
+
+	/// Record a failure at the given time.
+	///
+	/// Return the delay before the restart and whether to escalate.
+	/// Escalation happens only once until the failures fall below the limit within the window.
+	pub fn fail(&mut self, at: Instant) -> (Duration, bool) {
+		while self.failures.front().map(|t| at.duration_since(*t) > self.window).unwrap_or(false) {
+			self.failures.pop_front();
+		}
+
+		self.failures.push_back(at);
+
+		let escalate = if self.failures.len() >= self.max_failures {
+			!mem::replace(&mut self.escalated, true)
+		} else {
+			self.escalated = false;
+
+			false
+		};
+
+		(self.backoff.next(), escalate)
+	}
+
+	pub fn failures(&self) -> usize {
+		self.failures.len()
+	}
+}
+#[test]
+fn supervisor_should_work() {
+	let mut s = Supervisor::new(&Supervision { max_failures: 3, window: 60 });
+	let now = Instant::now();
+
+	assert!(!s.fail(now).1);
+	assert!(!s.fail(now + Duration::from_secs(1)).1);
+	assert!(s.fail(now + Duration::from_secs(2)).1);
+	// Escalate only once.
+	assert!(!s.fail(now + Duration::from_secs(3)).1);
+	assert_eq!(s.failures(), 4);
+
+	// The failures out of the window will be forgotten.
+	assert!(!s.fail(now + Duration::from_secs(120)).1);
+	assert_eq!(s.failures(), 1);
+	assert!(!s.fail(now + Duration::from_secs(121)).1);
+	assert!(s.fail(now + Duration::from_secs(122)).1);
+}
+
+impl Hunter {
+	/// Run the hunter and restart it on failures.
+	///
+	/// The persisted states, such as the bid index, will be reloaded by `initialize` on every
+	/// restart, and the state of the auction is carried over. Only the configuration errors are
+	/// fatal.
+	pub async fn supervise(&mut self) -> Result<()> {
+		let mut supervisor = Supervisor::new(&self.configuration.supervision);
+		let mut state = State::default();
+
+		while let Err(e) = self.start(&mut state).await {
+			let e = Error::from(e);
+
+			if let Error::Configuration(_) = e {
+				let n = format!("hunter has been stopped due to error({e})");
+
+				tracing::error!("{n}");
+
+				self.notify(&Context::new(NotificationEvent::Stopped { error: e.to_string() }, n));
+				// Give the last notifications a chance before exiting.
+				self.deliveries.flush(Duration::from_secs(30)).await;
+
+				return Err(e.into());
+			}
+
+			let (delay, escalate) = supervisor.fail(Instant::now());
+			let n = format!("hunter is restarting in {delay:?} due to error({e})");
+
+			tracing::error!("{n}");
+
+			self.notify(&Context::new(
+				NotificationEvent::Restarting {
+					error: e.to_string(),
+					failures: supervisor.failures(),
+				},
+				n,
+			));
+
+			if escalate {
+				let n = format!(
+					"hunter has failed {} times within {:?}, the last error is ({e}), please check it manually",
+					supervisor.failures(),
+					supervisor.window
+				);
+
+				tracing::error!("{n}");
+
+				self.notify(&Context::new(
+					NotificationEvent::Escalated {
+						error: e.to_string(),
+						failures: supervisor.failures(),
+					},
+					n,
+				));
+			}
+
+			tokio::time::sleep(delay).await;
+
+			if !self.ws_is_connected() {
+				tracing::error!("websocket connection was lost");
+
+				self.report_disconnected();
+				self.notify(&Context::new(
+					NotificationEvent::ConnectionLost,
+					"websocket connection was lost",
+				));
+
+				let mut tried = false;
+
+				while let Err(e) = self.ws_reconnect(&mut tried).await {
+					tracing::error!("failed to establish a websocket connection due to error({e})");
+				}
+
+				tracing::info!("websocket connection has been reestablished");
+
+				self.resolve(Condition::ConnectionLost);
+			} else if !self.ws_all_connected() {
+				// Only a backup is down, try it quietly once per restart.
+				if let Err(e) = self.ws_reconnect(&mut false).await {
+					tracing::warn!("failed to reconnect to the backup endpoints due to error({e})");
+				}
+			}
+		}
+
+		Ok(())
+	}
+}
@@ -1,5 +1,6 @@
 // crates.io
 use subxt::{
+	config::polkadot::H256,
 	dynamic::{self, Value},
 	tx::TxPayload,
 	Error,
@@ -8,35 +9,48 @@ use subxt::{
 use crate::hunter::*;
 
 impl Hunter {
-	async fn tx<C>(&self, call: &C) -> Result<DispatchResult>
+	/// Submit the call, return the extrinsic hash and the dispatch result.
+	///
+	/// Once signed, the hash is returned even if the submission fails, so it can still be audited.
+	async fn tx<C>(&self, call: &C) -> Result<(H256, Result<DispatchResult>)>
 	where
 		C: TxPayload,
 	{
-		match self
+		let extrinsic = self
 			.node
 			.tx()
-			.sign_and_submit_then_watch_default(call, &self.configuration.bid.delegate)
-			.await?
-			.wait_for_finalized()
-			.await?
-			.wait_for_success()
-			.await
-		{
-			Ok(r) => Ok(r
-				// Always using proxy in production, this must be some.
-				.find_first::<EProxyExecuted>()?
-				.map(EProxyExecuted::into_dispatch_result)
-				.unwrap_or_else(|| {
-					tracing::warn!("this log should only appear in a test");
-
-					Ok(())
-				})),
-			Err(Error::Runtime(e)) => Ok(Err(e.to_string())),
-			Err(e) => Err(e)?,
-		}
+			.create_signed(call, &self.configuration.bid.delegate, Default::default())
+			.await?;
+		let hash = extrinsic.hash();
+		let progress = extrinsic.submit_and_watch().await;
+
+		// Then submit to the other endpoints for the faster propagation, while waiting for the
+		// finalization. Not before, otherwise the active node might reject the watched one as
+		// already imported.
+		let (_, result) = tokio::join!(self.broadcast(extrinsic.encoded()), async {
+			Ok(match progress?.wait_for_finalized().await?.wait_for_success().await {
+				Ok(r) => r
+					// Always using proxy in production, this must be some.
+					.find_first::<EProxyExecuted>()?
+					.map(EProxyExecuted::into_dispatch_result)
+					.unwrap_or_else(|| {
+						tracing::warn!("this log should only appear in a test");
+
+						Ok(())
+					}),
+				Err(Error::Runtime(e)) => Err(e.to_string()),
+				Err(e) => Err(e)?,
+			})
+		});
+
+		Ok((hash, result))
 	}
 
-	pub async fn bid(&self, auction_index: u32, value: Balance) -> Result<DispatchResult> {
+	pub async fn bid(
+		&self,
+		auction_index: u32,
+		value: Balance,
+	) -> Result<(H256, Result<DispatchResult>)> {
 		let bid = dynamic::tx(
 			"Auctions",
 			"bid",
@@ -53,7 +67,7 @@ impl Hunter {
 		self.tx(&proxied_bid).await
 	}
 
-	pub async fn contribute(&self, value: Balance) -> Result<DispatchResult> {
+	pub async fn contribute(&self, value: Balance) -> Result<(H256, Result<DispatchResult>)> {
 		let contribute = dynamic::tx(
 			"Crowdloan",
 			"contribute",
@@ -89,7 +103,7 @@ async fn tx_should_work() {
 		);
 		let proxied_transfer = util::proxy_of(&hunter.configuration.bid.real, transfer);
 
-		assert!(hunter.tx(&proxied_transfer).await.unwrap().is_ok());
+		assert!(hunter.tx(&proxied_transfer).await.unwrap().1.unwrap().is_ok());
 	}
 
 	{
@@ -109,7 +123,7 @@ async fn tx_should_work() {
 			util::proxy_of(&hunter.configuration.bid.real, transfer_too_much);
 
 		assert_eq!(
-			hunter.tx(&proxied_transfer_too_much).await.unwrap(),
+			hunter.tx(&proxied_transfer_too_much).await.unwrap().1.unwrap(),
 			Err("Value { value: Variant(Variant { name: \"Token\", values: Unnamed([Value { value: Variant(Variant { name: \"FundsUnavailable\", values: Unnamed([]) }), context: 27 }]) }), context: 25 }".into())
 		);
 	}
@@ -126,7 +140,7 @@ async fn tx_should_work() {
 		);
 
 		assert_eq!(
-			hunter.tx(&transfer_too_much).await.unwrap(),
+			hunter.tx(&transfer_too_much).await.unwrap().1.unwrap(),
 			Err("Token error: Funds are unavailable.".into())
 		);
 	}
@@ -143,7 +157,7 @@ async fn tx_should_work() {
 			],
 		);
 
-		assert!(hunter.tx(&transfer_all).await.unwrap().is_ok());
+		assert!(hunter.tx(&transfer_all).await.unwrap().1.unwrap().is_ok());
 	}
 
 	{
@@ -162,7 +176,7 @@ async fn tx_should_work() {
 		let proxied_transfer = util::proxy_of(&hunter.configuration.bid.real, transfer);
 
 		assert_eq!(
-			hunter.tx(&proxied_transfer).await.unwrap_err().to_string(),
+			hunter.tx(&proxied_transfer).await.unwrap().1.unwrap_err().to_string(),
 			"Rpc error: RPC error: RPC call failed: ErrorObject { code: ServerError(1010), message: \"Invalid Transaction\", data: Some(RawValue(\"Inability to pay some fees (e.g. account balance too low)\")) }".to_string()
 		);
 	}
@@ -1,3 +1,5 @@
+// std
+use std::time::{Duration, Instant};
 // crates.io
 use parity_scale_codec::{Decode, Encode};
 use regex::Regex;
@@ -6,7 +8,12 @@ use reqwest::{
 	Client, ClientBuilder,
 };
 use scale_value::Composite;
-use serde::ser::Serializer;
+use serde::{
+	de::{Deserializer, Error as DeError},
+	ser::Serializer,
+	Deserialize,
+};
+use sp_core::crypto::{AccountId32, Ss58Codec};
 use sp_runtime::{traits::AccountIdConversion, TypeId};
 use subxt::{
 	dynamic::{self, Value},
@@ -24,7 +31,9 @@ pub fn check_ws_uri(uri: &str) -> bool {
 	Regex::new(r"^wss?://[^\s/$.?#].[^\s]*$").expect(E_REGEX_MUST_BE_VALID).is_match(uri)
 }
 pub fn check_smtp_uri(uri: &str) -> bool {
-	Regex::new(r"^[a-zA-Z0-9.-]+(:\d+)?$").expect(E_REGEX_MUST_BE_VALID).is_match(uri)
+	Regex::new(r"^([a-zA-Z0-9.-]+(:\d+)?|smtps?://[^\s/$.?#].[^\s]*)$")
+		.expect(E_REGEX_MUST_BE_VALID)
+		.is_match(uri)
 }
 #[test]
 fn check_uri_should_work() {
@@ -38,9 +47,16 @@ fn check_uri_should_work() {
 
 	assert!(check_smtp_uri("smtp.example.com"));
 	assert!(check_smtp_uri("smtp.example.com:587"));
+	assert!(check_smtp_uri("smtp://127.0.0.1:2525"));
+	assert!(check_smtp_uri("smtps://smtp.example.com:465"));
 	assert!(!check_smtp_uri("invalid url"));
 }
 
+/// Compare the secrets in constant time.
+pub fn constant_time_eq(a: &str, b: &str) -> bool {
+	a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0, |d, (a, b)| d | (a ^ b)) == 0
+}
+
 pub fn http_json_client() -> Client {
 	ClientBuilder::new()
 		.default_headers(HeaderMap::from_iter([(
@@ -251,12 +267,17 @@ fn combinations_should_work() {
 	);
 }
 
+pub fn blocks2seconds(blocks_count: BlockNumber) -> u64 {
+	const BLOCK_TIME: u64 = 6;
+
+	blocks_count as u64 * BLOCK_TIME
+}
+
 pub fn blocks2time(blocks_count: BlockNumber) -> String {
 	const HOUR: u64 = 60 * 60;
 	const DAY: u64 = HOUR * 24;
-	const BLOCK_TIME: u64 = 6;
 
-	let seconds = blocks_count as u64 * BLOCK_TIME;
+	let seconds = blocks2seconds(blocks_count);
 	let d = seconds / DAY;
 	let h = (seconds % DAY) / HOUR;
 	let m = (seconds % HOUR) / 60;
@@ -274,11 +295,145 @@ fn blocks2time_should_work() {
 	assert_eq!(blocks2time(123456), "8d:13h:45m:36s");
 }
 
+/// Render the rows as a Markdown table, which is readable in both the plain text and the chat apps.
+pub fn markdown_table(headers: &[&str], rows: &[Vec<String>]) -> String {
+	let row = |cells: &mut dyn Iterator<Item = &str>| {
+		format!("| {} |", cells.map(|c| c.replace('|', "\\|")).collect::<Vec<_>>().join(" | "))
+	};
+	let mut table =
+		vec![row(&mut headers.iter().copied()), row(&mut headers.iter().map(|_| "---"))];
+
+	table.extend(rows.iter().map(|r| row(&mut r.iter().map(String::as_str))));
+
+	table.join("\n")
+}
+#[test]
+fn markdown_table_should_work() {
+	assert_eq!(
+		markdown_table(
+			&["bidder", "amount"],
+			&[vec!["Alice".into(), "KSM(1)".into()], vec!["a|b".into(), "KSM(2)".into()]]
+		),
+		"| bidder | amount |\n| --- | --- |\n| Alice | KSM(1) |\n| a\\|b | KSM(2) |"
+	);
+}
+
+/// Encode the account id in the SS58 format of the connected network.
+///
+/// The SS58 prefix is set by `sp_core::crypto::set_default_ss58_version` once connected.
+pub fn ss58(account_id: &AccountId) -> String {
+	AccountId32::from(*account_id).to_ss58check()
+}
+
+/// Parse the account id from either a hex public key or a SS58 address.
+///
+/// The SS58 address must be of the given prefix if any, e.g. a Kusama address is rejected on
+/// Polkadot.
+pub fn account_id_of(s: &str, ss58_prefix: Option<u16>) -> Result<AccountId> {
+	if s.starts_with("0x") {
+		return array_bytes::hex2array(s).map_err(|e| anyhow::anyhow!("invalid public key, {e:?}"));
+	}
+
+	let (account_id, version) = AccountId32::from_ss58check_with_version(s)
+		.map_err(|e| anyhow::anyhow!("invalid SS58 address, {e}"))?;
+	let version = u16::from(version);
+
+	if let Some(p) = ss58_prefix.filter(|p| *p != version) {
+		Err(anyhow::anyhow!("SS58 address of prefix({version}), expected prefix({p})"))?;
+	}
+
+	Ok(account_id.into())
+}
+#[test]
+fn account_id_of_should_work() {
+	let alice = array_bytes::hex2array_unchecked(
+		"0xd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d",
+	);
+
+	assert_eq!(
+		account_id_of(
+			"0xd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d",
+			Some(0)
+		)
+		.unwrap(),
+		alice
+	);
+	assert_eq!(
+		account_id_of("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY", None).unwrap(),
+		alice
+	);
+	// Kusama.
+	assert_eq!(
+		account_id_of("HNZata7iMYWmk5RvZRTiAsSDhV8366zq2YGb3tLH5Upf74F", Some(2)).unwrap(),
+		alice
+	);
+	assert_eq!(
+		account_id_of("HNZata7iMYWmk5RvZRTiAsSDhV8366zq2YGb3tLH5Upf74F", Some(0))
+			.unwrap_err()
+			.to_string(),
+		"SS58 address of prefix(2), expected prefix(0)"
+	);
+	assert!(account_id_of("0xd435", None).is_err());
+	assert!(account_id_of("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQZ", None).is_err());
+	assert_eq!(ss58(&alice), "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY");
+}
+
 pub fn serialize_account_id<S>(account_id: &AccountId, serializer: S) -> StdResult<S::Ok, S::Error>
 where
 	S: Serializer,
 {
-	serializer.serialize_str(&array_bytes::bytes2hex("0x", account_id))
+	serializer.serialize_str(&ss58(account_id))
+}
+
+/// Deserialize the account id from either a hex public key or a SS58 address.
+pub fn deserialize_account_id<'de, D>(deserializer: D) -> StdResult<AccountId, D::Error>
+where
+	D: Deserializer<'de>,
+{
+	account_id_of(&String::deserialize(deserializer)?, None)
+		.map_err(|e| D::Error::custom(format!("invalid account id, {e}")))
+}
+
+/// A number which is encoded as either a number or a string.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Number {
+	String(String),
+	Number(u64),
+}
+impl Number {
+	fn parse<E, N>(self) -> StdResult<N, E>
+	where
+		E: DeError,
+		N: TryFrom<u128>,
+	{
+		let n = match self {
+			Self::String(s) => s.parse().map_err(E::custom)?,
+			Self::Number(n) => n as _,
+		};
+
+		N::try_from(n).map_err(|_| E::custom("number overflow"))
+	}
+}
+
+/// Deserialize a number from either a number or a string.
+///
+/// Big numbers are usually encoded as strings in JSON.
+pub fn deserialize_number<'de, D, N>(deserializer: D) -> StdResult<N, D::Error>
+where
+	D: Deserializer<'de>,
+	N: TryFrom<u128>,
+{
+	Number::deserialize(deserializer)?.parse()
+}
+
+/// Like [`deserialize_number`], but `null` will be deserialized as `None`.
+pub fn deserialize_option_number<'de, D, N>(deserializer: D) -> StdResult<Option<N>, D::Error>
+where
+	D: Deserializer<'de>,
+	N: TryFrom<u128>,
+{
+	Option::<Number>::deserialize(deserializer)?.map(Number::parse).transpose()
 }
 
 pub fn proxy_of(real: &AccountId, call: Payload<Composite<()>>) -> Payload<Composite<()>> {
@@ -292,3 +447,63 @@ pub fn proxy_of(real: &AccountId, call: Payload<Composite<()>>) -> Payload<Compo
 		],
 	)
 }
+
+/// Exponential backoff, which will be reset if the last failure is old enough.
+#[derive(Debug)]
+pub struct Backoff {
+	min: Duration,
+	max: Duration,
+	delay: Duration,
+	last_failure_at: Option<Instant>,
+}
+impl Backoff {
+	const RESET_AFTER: Duration = Duration::from_secs(300);
+
+	/// Start from `min` and double up to `max`.
+	pub fn new(min: Duration, max: Duration) -> Self {
+		Self { min, max, delay: Duration::ZERO, last_failure_at: None }
+	}
+
+	/// Record a failure and return the delay before the next retry.
+	pub fn next(&mut self) -> Duration {
+		let now = Instant::now();
+
+		self.delay = match self.last_failure_at {
+			Some(t) if now.duration_since(t) < Self::RESET_AFTER => (self.delay * 2).min(self.max),
+			_ => self.min,
+		};
+		self.last_failure_at = Some(now);
+
+		self.delay
+	}
+}
+impl Default for Backoff {
+	fn default() -> Self {
+		Self::new(Duration::from_secs(1), Duration::from_secs(60))
+	}
+}
+#[test]
+fn backoff_should_work() {
+	let mut b = Backoff::default();
+
+	assert_eq!(b.next(), Duration::from_secs(1));
+	assert_eq!(b.next(), Duration::from_secs(2));
+	assert_eq!(b.next(), Duration::from_secs(4));
+
+	(0..10).for_each(|_| {
+		b.next();
+	});
+
+	assert_eq!(b.next(), Duration::from_secs(60));
+
+	b.last_failure_at = Instant::now().checked_sub(Duration::from_secs(301));
+
+	assert_eq!(b.next(), Duration::from_secs(1));
+
+	let mut b = Backoff::new(Duration::from_millis(250), Duration::from_secs(1));
+
+	assert_eq!(b.next(), Duration::from_millis(250));
+	assert_eq!(b.next(), Duration::from_millis(500));
+	assert_eq!(b.next(), Duration::from_secs(1));
+	assert_eq!(b.next(), Duration::from_secs(1));
+}
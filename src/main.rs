@@ -18,7 +18,7 @@ mod prelude {
 // std
 use std::path::PathBuf;
 // crates.io
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
 
 #[derive(Debug, Parser)]
 #[command(
@@ -42,8 +42,73 @@ struct Cli {
 	///   Linux:   /home/alice/.config/slothunter
 	///   Windows: C:\Users\Alice\AppData\Roaming\slothunter
 	///   MacOS:   /Users/Alice/Library/Application Support/slothunter
-	#[arg(long, short, value_name = "PATH", verbatim_doc_comment)]
+	#[arg(long, short, value_name = "PATH", global = true, verbatim_doc_comment)]
 	configuration: Option<PathBuf>,
+	#[command(subcommand)]
+	subcommand: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+	/// Export the auction history of every block within a range.
+	///
+	/// The range defaults to the ending period of the current auction.
+	/// An archive node is required to export the history of the previous auctions.
+	Export {
+		/// Start of the block range.
+		#[arg(long, value_name = "BLOCK_NUMBER")]
+		from: Option<BlockNumber>,
+		/// End of the block range.
+		#[arg(long, value_name = "BLOCK_NUMBER")]
+		to: Option<BlockNumber>,
+		/// Output format.
+		///
+		/// Defaults to the format indicated by the output's extension.
+		#[arg(long, value_name = "FORMAT")]
+		format: Option<ExportFormat>,
+		/// Path to the output file.
+		#[arg(long, short, value_name = "PATH")]
+		output: PathBuf,
+	},
+	/// Run every check against the configuration and the chain, then report all the problems.
+	CheckConfig {
+		/// Output the report as JSON.
+		#[arg(long)]
+		json: bool,
+	},
+	/// Inspect the audit log.
+	Audit {
+		#[command(subcommand)]
+		subcommand: AuditCommand,
+	},
+}
+
+#[derive(Debug, Subcommand)]
+enum AuditCommand {
+	/// Verify the hash chain of the audit log.
+	Verify {
+		/// Path to the audit log.
+		///
+		/// Defaults to the `path` of the configured `[audit]` section.
+		#[arg(long, short, value_name = "PATH")]
+		path: Option<PathBuf>,
+	},
+}
+
+#[derive(Clone, Debug, ValueEnum)]
+enum ExportFormat {
+	Csv,
+	JsonLines,
+	Parquet,
+}
+impl From<ExportFormat> for RecordFormat {
+	fn from(format: ExportFormat) -> Self {
+		match format {
+			ExportFormat::Csv => Self::Csv,
+			ExportFormat::JsonLines => Self::JsonLines,
+			ExportFormat::Parquet => Self::Parquet,
+		}
+	}
 }
 
 #[tokio::main]
@@ -51,27 +116,55 @@ async fn main() -> Result<()> {
 	color_eyre::install().map_err(|e| anyhow::anyhow!(e))?;
 	tracing_subscriber::fmt::init();
 
-	let Cli { configuration } = Cli::parse();
-	let mut hunter = Hunter::from_configuration(
-		ConfigurationToml::load(configuration)?.try_into_configuration()?,
-	)
-	.await?;
+	let Cli { configuration, subcommand } = Cli::parse();
+	let configuration = ConfigurationToml::locate(configuration)?;
 
-	while let Err(e) = hunter.start().await {
-		if hunter.ws_is_connected() {
-			panic!("{e}");
+	if let Some(Command::CheckConfig { json }) = subcommand {
+		let diagnosis = ConfigurationToml::diagnose_file(&configuration).await;
+
+		if json {
+			println!("{}", serde_json::to_string_pretty(&diagnosis)?);
 		} else {
-			tracing::error!("websocket connection was lost due to error({e})");
+			println!("{}", diagnosis.to_human());
+		}
+
+		return diagnosis.into_result();
+	}
+	if let Some(Command::Audit { subcommand: AuditCommand::Verify { path } }) = &subcommand {
+		let path = match path {
+			Some(p) => p.to_owned(),
+			None =>
+				ConfigurationToml::load(&configuration)?
+					.audit
+					.ok_or_else(|| {
+						anyhow::anyhow!("no `[audit]` section, specify the log with `--path`")
+					})?
+					.path,
+		};
+		let (records, hash) = hunter::verify_file(&path)?;
 
-			let mut tried = false;
+		println!(
+			"{records} record(s) of {} have been verified, the last hash is {hash}",
+			path.display()
+		);
 
-			while let Err(e) = hunter.ws_reconnect(&mut tried).await {
-				tracing::error!("failed to establish a websocket connection due to error({e})");
-			}
+		return Ok(());
+	}
 
-			tracing::info!("websocket connection has been reestablished");
-		}
+	let mut hunter = Hunter::from_configuration(
+		ConfigurationToml::load(&configuration)?.try_into_configuration()?,
+	)
+	.await?;
+
+	if let Some(Command::Export { from, to, format, output }) = subcommand {
+		let format = format.map_or_else(|| RecordFormat::of(&output), |f| Ok(f.into()))?;
+
+		return hunter.export(from, to, &output, format).await;
 	}
 
-	Ok(())
+	hunter.serve_api()?;
+	hunter.serve_bot();
+	hunter.watch_configuration(configuration);
+
+	hunter.supervise().await
 }
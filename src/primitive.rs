@@ -14,12 +14,21 @@ pub type Balance = u128;
 pub type ParaId = u32;
 pub type SlotRange = (u32, u32);
 
-#[derive(Debug, Serialize)]
+#[derive(Clone, Debug, Serialize)]
 pub struct AuctionDetail {
 	pub index: u32,
 	pub first_lease_period: u32,
 	pub ending_period_start_at: BlockNumber,
 }
+impl From<&EAuctionStarted> for AuctionDetail {
+	fn from(e: &EAuctionStarted) -> Self {
+		Self {
+			index: e.auction_index,
+			first_lease_period: e.lease_period,
+			ending_period_start_at: e.ending,
+		}
+	}
+}
 impl AuctionDetail {
 	pub fn fmt(&self, now: BlockNumber, end_at: BlockNumber) -> String {
 		let remain_blocks = end_at.saturating_sub(now);
@@ -43,11 +52,11 @@ pub struct Bidder {
 	pub last_accepted_bid: Option<AcceptedBid>,
 }
 impl Bidder {
-	pub fn fmt(&self, token: &Token) -> String {
+	pub fn fmt(&self, token: &Token, names: &Names) -> String {
 		format!(
 			"bidder({}, {}) has bid with extra reservation {} this turn{}",
-			array_bytes::bytes2hex("0x", self.who),
-			self.para_id,
+			names.account(&self.who),
+			names.para(self.para_id),
 			token.fmt(self.reserved),
 			if self.existing_deposit == 0 {
 				"".into()
@@ -93,10 +102,10 @@ impl Winning {
 		))
 	}
 
-	pub fn fmt(&self, token: &Token, first_lease_period: u32) -> Vec<String> {
+	pub fn fmt(&self, token: &Token, names: &Names, first_lease_period: u32) -> Vec<String> {
 		self.0
 			.iter()
-			.filter_map(|w| w.as_ref().map(|w| w.fmt(token, first_lease_period)))
+			.filter_map(|w| w.as_ref().map(|w| w.fmt(token, names, first_lease_period)))
 			.collect::<Vec<_>>()
 	}
 
@@ -214,9 +223,8 @@ impl Default for Winning {
 		Self([None; 36])
 	}
 }
-#[derive(Clone, Copy, Default, Serialize)]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize)]
 #[cfg_attr(not(test), derive(Debug))]
-#[cfg_attr(test, derive(PartialEq))]
 pub struct Winner {
 	#[serde(serialize_with = "util::serialize_account_id")]
 	pub who: AccountId,
@@ -229,11 +237,11 @@ impl Winner {
 		Self { who: s_winner.0, para_id: s_winner.1, leases, value: s_winner.2 }
 	}
 
-	pub fn fmt(&self, token: &Token, first_lease_period: u32) -> String {
+	pub fn fmt(&self, token: &Token, names: &Names, first_lease_period: u32) -> String {
 		format!(
 			"bidder({}, {}) has won the lease(s)[#{}, #{}] with {}",
-			array_bytes::bytes2hex("0x", self.who),
-			self.para_id,
+			names.account(&self.who),
+			names.para(self.para_id),
 			first_lease_period + self.leases.0,
 			first_lease_period + self.leases.1,
 			token.fmt(self.value),
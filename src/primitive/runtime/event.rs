@@ -1,60 +1,148 @@
 // crates.io
 use scale_decode::DecodeAsType;
-use serde::Deserialize;
-use serde_json::Value;
-use subxt::{dynamic::DecodedValue, events::StaticEvent};
+use serde::Serialize;
+use subxt::{
+	dynamic::DecodedValue,
+	events::{EventDetails, Phase, StaticEvent},
+	PolkadotConfig,
+};
 // slothunter
 use crate::hunter::*;
 
-#[derive(Debug, Deserialize)]
-pub struct EResponse<E> {
-	pub data: Events<E>,
+#[derive(Debug, DecodeAsType)]
+pub struct EProxyExecuted {
+	pub result: StdResult<(), DecodedValue>,
+}
+impl EProxyExecuted {
+	pub fn into_dispatch_result(self) -> DispatchResult {
+		match self.result {
+			Ok(()) => Ok(()),
+			Err(v) => Err(format!("{v:?}")),
+		}
+	}
+}
+impl StaticEvent for EProxyExecuted {
+	const EVENT: &'static str = "ProxyExecuted";
+	const PALLET: &'static str = "Proxy";
 }
 
-#[derive(Debug, Deserialize)]
-pub struct Events<E> {
-	pub events: Vec<E>,
+macro_rules! impl_static_event {
+	($e:ident, $pallet:expr, $event:expr) => {
+		impl StaticEvent for $e {
+			const EVENT: &'static str = $event;
+			const PALLET: &'static str = $pallet;
+		}
+	};
+}
+
+// https://github.com/paritytech/polkadot/blob/b1cc6fa14330261a305d56be36c04e9c99518993/runtime/common/src/auctions.rs#L153
+#[derive(Clone, Debug, DecodeAsType, Serialize)]
+pub struct EAuctionStarted {
+	pub auction_index: u32,
+	pub lease_period: u32,
+	pub ending: BlockNumber,
 }
+impl_static_event!(EAuctionStarted, "Auctions", "AuctionStarted");
 
-#[derive(Debug, Deserialize)]
-pub struct RawEvent {
-	pub args: Value,
-	pub block: Value,
+#[derive(Clone, Debug, DecodeAsType, Serialize)]
+pub struct EAuctionClosed {
+	pub auction_index: u32,
 }
+impl_static_event!(EAuctionClosed, "Auctions", "AuctionClosed");
 
-#[derive(Debug, Deserialize)]
-pub struct Event<E> {
-	pub args: E,
-	pub block: BlockResponse,
+#[derive(Clone, Debug, DecodeAsType, Serialize)]
+pub struct EReserved {
+	#[serde(serialize_with = "util::serialize_account_id")]
+	pub bidder: AccountId,
+	pub extra_reserved: Balance,
+	pub total_amount: Balance,
 }
+impl_static_event!(EReserved, "Auctions", "Reserved");
 
-#[derive(Debug, Deserialize)]
-pub struct BlockResponse {
-	pub height: BlockNumber,
+#[derive(Clone, Debug, DecodeAsType, Serialize)]
+pub struct EUnreserved {
+	#[serde(serialize_with = "util::serialize_account_id")]
+	pub bidder: AccountId,
+	pub amount: Balance,
 }
+impl_static_event!(EUnreserved, "Auctions", "Unreserved");
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, DecodeAsType, Serialize)]
 pub struct EBidAccepted {
-	pub amount: String,
-	#[serde(rename(deserialize = "firstSlot"))]
+	#[serde(serialize_with = "util::serialize_account_id")]
+	pub bidder: AccountId,
+	pub para_id: ParaId,
+	pub amount: Balance,
 	pub first_slot: u32,
-	#[serde(rename(deserialize = "lastSlot"))]
 	pub last_slot: u32,
 }
+impl_static_event!(EBidAccepted, "Auctions", "BidAccepted");
 
-#[derive(Debug, DecodeAsType)]
-pub struct EProxyExecuted {
-	pub result: StdResult<(), DecodedValue>,
+#[derive(Clone, Debug, DecodeAsType, Serialize)]
+pub struct EWinningOffset {
+	pub auction_index: u32,
+	pub block_number: BlockNumber,
 }
-impl EProxyExecuted {
-	pub fn into_dispatch_result(self) -> DispatchResult {
-		match self.result {
-			Ok(()) => Ok(()),
-			Err(v) => Err(format!("{v:?}")),
-		}
+impl_static_event!(EWinningOffset, "Auctions", "WinningOffset");
+
+// https://github.com/paritytech/polkadot/blob/b1cc6fa14330261a305d56be36c04e9c99518993/runtime/common/src/slots/mod.rs#L131
+#[derive(Clone, Debug, DecodeAsType, Serialize)]
+pub struct ELeased {
+	pub para_id: ParaId,
+	#[serde(serialize_with = "util::serialize_account_id")]
+	pub leaser: AccountId,
+	pub period_begin: u32,
+	pub period_count: u32,
+	pub extra_reserved: Balance,
+	pub total_amount: Balance,
+}
+impl_static_event!(ELeased, "Slots", "Leased");
+
+#[derive(Clone, Debug, Serialize)]
+pub enum AuctionEvent {
+	AuctionStarted(EAuctionStarted),
+	AuctionClosed(EAuctionClosed),
+	Reserved(EReserved),
+	Unreserved(EUnreserved),
+	BidAccepted(EBidAccepted),
+	WinningOffset(EWinningOffset),
+	Leased(ELeased),
+}
+impl AuctionEvent {
+	pub fn decode(event: &EventDetails<PolkadotConfig>) -> Result<Option<Self>> {
+		let e = match (event.pallet_name(), event.variant_name()) {
+			("Auctions", "AuctionStarted") =>
+				event.as_event::<EAuctionStarted>()?.map(Self::AuctionStarted),
+			("Auctions", "AuctionClosed") =>
+				event.as_event::<EAuctionClosed>()?.map(Self::AuctionClosed),
+			("Auctions", "Reserved") => event.as_event::<EReserved>()?.map(Self::Reserved),
+			("Auctions", "Unreserved") => event.as_event::<EUnreserved>()?.map(Self::Unreserved),
+			("Auctions", "BidAccepted") => event.as_event::<EBidAccepted>()?.map(Self::BidAccepted),
+			("Auctions", "WinningOffset") =>
+				event.as_event::<EWinningOffset>()?.map(Self::WinningOffset),
+			("Slots", "Leased") => event.as_event::<ELeased>()?.map(Self::Leased),
+			_ => None,
+		};
+
+		Ok(e)
 	}
 }
-impl StaticEvent for EProxyExecuted {
-	const EVENT: &'static str = "ProxyExecuted";
-	const PALLET: &'static str = "Proxy";
+
+/// An event along with the index of the extrinsic which emitted it.
+///
+/// `extrinsic_index` is `None` if the event was emitted during initialization or finalization.
+#[derive(Clone, Debug, Serialize)]
+pub struct Phased<E> {
+	pub extrinsic_index: Option<u32>,
+	pub event: E,
+}
+impl<E> Phased<E> {
+	pub fn new(phase: Phase, event: E) -> Self {
+		let extrinsic_index = match phase {
+			Phase::ApplyExtrinsic(i) => Some(i),
+			_ => None,
+		};
+
+		Self { extrinsic_index, event }
+	}
 }